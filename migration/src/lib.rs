@@ -4,6 +4,24 @@ mod m20220101_000001_create_table;
 mod m20260215_070659_add_check_in_column;
 mod m20260216_064755_add_unique_id_column;
 mod m20260220_022947_add_cron_column_to_jobs;
+mod m20261017_090000_add_host_column_to_jobs;
+mod m20261017_091500_add_cancelled_status;
+mod m20261017_093000_add_max_attempts_column_to_jobs;
+mod m20261017_100000_add_status_updated_at_index;
+mod m20261017_103000_create_job_attempt_table;
+mod m20261017_110000_add_response_columns_to_jobs;
+mod m20261017_113000_add_timeout_column_to_jobs;
+mod m20261017_120000_add_retry_policy_column_to_jobs;
+mod m20261017_123000_add_failure_reason_columns;
+mod m20261017_130000_add_body_encoding_columns_to_jobs;
+mod m20261017_133000_add_response_capture_columns;
+mod m20261017_140000_add_signing_secret_column_to_jobs;
+mod m20261017_150000_add_encryption_columns_to_jobs;
+mod m20261017_160000_add_job_ready_notify_trigger;
+mod m20261017_170000_add_priority_column_to_jobs;
+mod m20261017_180000_add_queues;
+mod m20261017_190000_add_scheduled_at_column_to_jobs;
+mod m20261017_200000_add_connect_timeout_column_to_jobs;
 
 pub struct Migrator;
 
@@ -15,6 +33,24 @@ impl MigratorTrait for Migrator {
             Box::new(m20260215_070659_add_check_in_column::Migration),
             Box::new(m20260216_064755_add_unique_id_column::Migration),
             Box::new(m20260220_022947_add_cron_column_to_jobs::Migration),
+            Box::new(m20261017_090000_add_host_column_to_jobs::Migration),
+            Box::new(m20261017_091500_add_cancelled_status::Migration),
+            Box::new(m20261017_093000_add_max_attempts_column_to_jobs::Migration),
+            Box::new(m20261017_100000_add_status_updated_at_index::Migration),
+            Box::new(m20261017_103000_create_job_attempt_table::Migration),
+            Box::new(m20261017_110000_add_response_columns_to_jobs::Migration),
+            Box::new(m20261017_113000_add_timeout_column_to_jobs::Migration),
+            Box::new(m20261017_120000_add_retry_policy_column_to_jobs::Migration),
+            Box::new(m20261017_123000_add_failure_reason_columns::Migration),
+            Box::new(m20261017_130000_add_body_encoding_columns_to_jobs::Migration),
+            Box::new(m20261017_133000_add_response_capture_columns::Migration),
+            Box::new(m20261017_140000_add_signing_secret_column_to_jobs::Migration),
+            Box::new(m20261017_150000_add_encryption_columns_to_jobs::Migration),
+            Box::new(m20261017_160000_add_job_ready_notify_trigger::Migration),
+            Box::new(m20261017_170000_add_priority_column_to_jobs::Migration),
+            Box::new(m20261017_180000_add_queues::Migration),
+            Box::new(m20261017_190000_add_scheduled_at_column_to_jobs::Migration),
+            Box::new(m20261017_200000_add_connect_timeout_column_to_jobs::Migration),
         ]
     }
 }
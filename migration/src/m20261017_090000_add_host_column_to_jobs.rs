@@ -0,0 +1,91 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Job::Table)
+                    .add_column(ColumnDef::new(Job::Host).string().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // Backfill the host from the stored URL so existing jobs can be filtered on
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "UPDATE job SET host = lower(substring(url from '^[A-Za-z][A-Za-z0-9+.-]*://(?:[^@/?#]*@)?([^:/?#]+)'))",
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-job-created-at-id")
+                    .table(Job::Table)
+                    .col(Job::CreatedAt)
+                    .col(Job::Id)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-job-host-created-at")
+                    .table(Job::Table)
+                    .col(Job::Host)
+                    .col(Job::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-job-status-created-at")
+                    .table(Job::Table)
+                    .col(Job::Status)
+                    .col(Job::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for name in [
+            "idx-job-status-created-at",
+            "idx-job-host-created-at",
+            "idx-job-created-at-id",
+        ] {
+            manager
+                .drop_index(Index::drop().name(name).table(Job::Table).to_owned())
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Job::Table)
+                    .drop_column(Job::Host)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Job {
+    Table,
+    Id,
+    Host,
+    Status,
+    CreatedAt,
+}
@@ -0,0 +1,45 @@
+use sea_orm_migration::prelude::extension::postgres::Type;
+use sea_orm_migration::prelude::*;
+
+#[derive(Iden)]
+pub enum StatusEnum {
+    Table,
+    #[iden = "Cancelled"]
+    Cancelled,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_type(
+                Type::alter()
+                    .name(StatusEnum::Table)
+                    .add_value(StatusEnum::Cancelled)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Postgres cannot drop a single enum value, so the type is rebuilt without it
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "UPDATE job SET status = 'Failure' WHERE status = 'Cancelled';
+                ALTER TYPE status_enum RENAME TO status_enum_old;
+                CREATE TYPE status_enum AS ENUM ('Pending', 'Running', 'Success', 'Failure');
+                ALTER TABLE job ALTER COLUMN status DROP DEFAULT;
+                ALTER TABLE job ALTER COLUMN status TYPE status_enum USING status::text::status_enum;
+                ALTER TABLE job ALTER COLUMN status SET DEFAULT 'Pending'::status_enum;
+                DROP TYPE status_enum_old;",
+            )
+            .await?;
+
+        Ok(())
+    }
+}
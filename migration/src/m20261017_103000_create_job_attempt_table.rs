@@ -0,0 +1,64 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // No foreign key to job: re-keying a job replaces its row, and the
+        // history has to survive that. Deleting a job removes its attempts.
+        manager
+            .create_table(
+                Table::create()
+                    .table(JobAttempt::Table)
+                    .if_not_exists()
+                    .col(uuid(JobAttempt::Id).primary_key())
+                    .col(uuid(JobAttempt::JobId))
+                    .col(integer(JobAttempt::Attempt))
+                    .col(string(JobAttempt::WorkerId))
+                    .col(timestamp(JobAttempt::StartedAt))
+                    .col(timestamp(JobAttempt::FinishedAt))
+                    .col(big_integer(JobAttempt::LatencyMs))
+                    .col(integer_null(JobAttempt::ResponseStatus))
+                    .col(json_null(JobAttempt::ResponseHeaders))
+                    .col(text_null(JobAttempt::ResponseBody))
+                    .col(text_null(JobAttempt::Error))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-job-attempt-job-id-started-at")
+                    .table(JobAttempt::Table)
+                    .col(JobAttempt::JobId)
+                    .col(JobAttempt::StartedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(JobAttempt::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum JobAttempt {
+    Table,
+    Id,
+    JobId,
+    Attempt,
+    WorkerId,
+    StartedAt,
+    FinishedAt,
+    LatencyMs,
+    ResponseStatus,
+    ResponseHeaders,
+    ResponseBody,
+    Error,
+}
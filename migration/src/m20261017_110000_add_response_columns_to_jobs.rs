@@ -0,0 +1,41 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Job::Table)
+                    .add_column(ColumnDef::new(Job::ResponseStatus).integer().null())
+                    .add_column(ColumnDef::new(Job::ResponseHeaders).json().null())
+                    .add_column(ColumnDef::new(Job::ResponseBody).text().null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Job::Table)
+                    .drop_column(Job::ResponseStatus)
+                    .drop_column(Job::ResponseHeaders)
+                    .drop_column(Job::ResponseBody)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Job {
+    Table,
+    ResponseStatus,
+    ResponseHeaders,
+    ResponseBody,
+}
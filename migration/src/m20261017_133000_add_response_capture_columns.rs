@@ -0,0 +1,73 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Job::Table)
+                    .add_column(ColumnDef::new(Job::ResponseTruncated).boolean().null())
+                    .add_column(ColumnDef::new(Job::ResponseLength).big_integer().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(JobAttempt::Table)
+                    .add_column(
+                        ColumnDef::new(JobAttempt::ResponseTruncated)
+                            .boolean()
+                            .null(),
+                    )
+                    .add_column(
+                        ColumnDef::new(JobAttempt::ResponseLength)
+                            .big_integer()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(JobAttempt::Table)
+                    .drop_column(JobAttempt::ResponseTruncated)
+                    .drop_column(JobAttempt::ResponseLength)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Job::Table)
+                    .drop_column(Job::ResponseTruncated)
+                    .drop_column(Job::ResponseLength)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Job {
+    Table,
+    ResponseTruncated,
+    ResponseLength,
+}
+
+#[derive(DeriveIden)]
+enum JobAttempt {
+    Table,
+    ResponseTruncated,
+    ResponseLength,
+}
@@ -0,0 +1,79 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // SQLite has no LISTEN/NOTIFY, workers there only poll
+        if manager.get_database_backend() != DbBackend::Postgres {
+            return Ok(());
+        }
+
+        // Postgres folds identical notifications within a transaction, so a batch
+        // insert wakes the workers once
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "CREATE OR REPLACE FUNCTION notify_job_ready()
+        RETURNS TRIGGER AS $$
+        BEGIN
+            PERFORM pg_notify('badger_job_ready', '');
+            RETURN NULL;
+        END;
+        $$ LANGUAGE plpgsql;",
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "CREATE TRIGGER notify_job_ready_on_insert
+                AFTER INSERT ON job
+                FOR EACH ROW
+                WHEN (NEW.status = 'Pending' AND NEW.next_run_at <= now() AT TIME ZONE 'UTC')
+                EXECUTE FUNCTION notify_job_ready();",
+            )
+            .await?;
+
+        // Jobs requeued through the API are due right away as well
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "CREATE TRIGGER notify_job_ready_on_requeue
+                AFTER UPDATE OF status ON job
+                FOR EACH ROW
+                WHEN (OLD.status <> 'Pending' AND NEW.status = 'Pending'
+                      AND NEW.next_run_at <= now() AT TIME ZONE 'UTC')
+                EXECUTE FUNCTION notify_job_ready();",
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != DbBackend::Postgres {
+            return Ok(());
+        }
+
+        manager
+            .get_connection()
+            .execute_unprepared("DROP TRIGGER IF EXISTS notify_job_ready_on_requeue ON job;")
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared("DROP TRIGGER IF EXISTS notify_job_ready_on_insert ON job;")
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared("DROP FUNCTION IF EXISTS notify_job_ready();")
+            .await?;
+
+        Ok(())
+    }
+}
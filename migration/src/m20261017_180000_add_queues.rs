@@ -0,0 +1,112 @@
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Job::Table)
+                    .add_column(
+                        ColumnDef::new(Job::Queue)
+                            .string()
+                            .not_null()
+                            .default("default"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Every claim is now for one queue
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-job-status-priority-next-run-at")
+                    .table(Job::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-job-queue-status-priority-next-run-at")
+                    .table(Job::Table)
+                    .col(Job::Queue)
+                    .col(Job::Status)
+                    .col((Job::Priority, IndexOrder::Desc))
+                    .col(Job::NextRunAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Only queues that have been paused or resumed have a row
+        manager
+            .create_table(
+                Table::create()
+                    .table(Queue::Table)
+                    .if_not_exists()
+                    .col(string(Queue::Name).primary_key())
+                    .col(boolean(Queue::Paused).default(false))
+                    .col(timestamp(Queue::UpdatedAt))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Queue::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx-job-queue-status-priority-next-run-at")
+                    .table(Job::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx-job-status-priority-next-run-at")
+                    .table(Job::Table)
+                    .col(Job::Status)
+                    .col((Job::Priority, IndexOrder::Desc))
+                    .col(Job::NextRunAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Job::Table)
+                    .drop_column(Job::Queue)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Job {
+    Table,
+    Queue,
+    Status,
+    Priority,
+    NextRunAt,
+}
+
+#[derive(DeriveIden)]
+enum Queue {
+    Table,
+    Name,
+    Paused,
+    UpdatedAt,
+}
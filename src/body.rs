@@ -0,0 +1,194 @@
+use crate::entity::sea_orm_active_enums::BodyEncoding;
+use crate::error::FieldError;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use reqwest::header::HeaderValue;
+use serde_json::Value as JsonValue;
+
+/// Content type sent when the job does not set one
+pub fn default_content_type(encoding: BodyEncoding) -> &'static str {
+    match encoding {
+        BodyEncoding::Json => "application/json",
+        BodyEncoding::Text => "text/plain; charset=utf-8",
+        BodyEncoding::Form => "application/x-www-form-urlencoded",
+        BodyEncoding::Base64 => "application/octet-stream",
+    }
+}
+
+/// Checks that `body` has the shape its encoding expects
+pub fn validate(
+    body: Option<&JsonValue>,
+    encoding: BodyEncoding,
+    content_type: Option<&str>,
+    errors: &mut Vec<FieldError>,
+) {
+    if let Some(content_type) = content_type
+        && (!content_type.contains('/') || HeaderValue::from_str(content_type).is_err())
+    {
+        errors.push(FieldError::new(
+            "content_type",
+            format!("invalid content type: {:?}", content_type),
+        ));
+    }
+
+    if let Some(body) = body.filter(|body| !body.is_null())
+        && let Err(message) = encode(body, encoding)
+    {
+        errors.push(FieldError::new("body", message));
+    }
+}
+
+/// The bytes sent on the wire for `body`
+pub fn encode(body: &JsonValue, encoding: BodyEncoding) -> Result<Vec<u8>, String> {
+    match encoding {
+        BodyEncoding::Json => Ok(body.to_string().into_bytes()),
+        BodyEncoding::Text => body
+            .as_str()
+            .map(|text| text.as_bytes().to_vec())
+            .ok_or_else(|| "a text body must be a JSON string".to_string()),
+        BodyEncoding::Form => encode_form(body).map(String::into_bytes),
+        BodyEncoding::Base64 => {
+            let data = body
+                .as_str()
+                .ok_or_else(|| "a base64 body must be a JSON string".to_string())?;
+            STANDARD
+                .decode(data)
+                .map_err(|e| format!("invalid base64 body: {}", e))
+        }
+    }
+}
+
+/// Form fields come from a JSON object. Arrays repeat the key, scalars are sent as text.
+fn encode_form(body: &JsonValue) -> Result<String, String> {
+    let map = body
+        .as_object()
+        .ok_or_else(|| "a form body must be a JSON object".to_string())?;
+
+    let mut form = url::form_urlencoded::Serializer::new(String::new());
+    for (key, value) in map {
+        let values = match value {
+            JsonValue::Array(items) => items.iter().collect(),
+            value => vec![value],
+        };
+        for value in values {
+            let text = match value {
+                JsonValue::String(s) => s.clone(),
+                JsonValue::Number(n) => n.to_string(),
+                JsonValue::Bool(b) => b.to_string(),
+                _ => {
+                    return Err(format!(
+                        "form field {:?} must be a string, number, boolean or an array of them",
+                        key
+                    ));
+                }
+            };
+            form.append_pair(key, &text);
+        }
+    }
+    Ok(form.finish())
+}
+
+/// The body as it goes into the fingerprint. JSON bodies with the default content
+/// type go in as they are, others are reduced to what is sent, tagged with the
+/// encoding and content type.
+pub fn fingerprint(
+    body: Option<&JsonValue>,
+    encoding: BodyEncoding,
+    content_type: Option<&str>,
+) -> String {
+    let content_type = content_type
+        .unwrap_or(default_content_type(encoding))
+        .to_ascii_lowercase();
+    let Some(body) = body.filter(|body| !body.is_null()) else {
+        return String::new();
+    };
+    if encoding == BodyEncoding::Json && content_type == default_content_type(encoding) {
+        return body.to_string();
+    }
+
+    let wire = match encode(body, encoding) {
+        // Raw bytes need not be UTF-8, so they are fingerprinted in base64
+        Ok(bytes) if encoding == BodyEncoding::Base64 => STANDARD.encode(bytes),
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(_) => body.to_string(),
+    };
+    format!("{}:{}:{}", encoding.as_str(), content_type, wire)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_encode() {
+        let form = encode(
+            &json!({"amount": 10, "paid": true, "tag": ["a", "b c"]}),
+            BodyEncoding::Form,
+        )
+        .unwrap();
+        assert_eq!(form, b"amount=10&paid=true&tag=a&tag=b+c");
+        assert!(
+            encode(&json!({"nested": {"key": "value"}}), BodyEncoding::Form).is_err(),
+            "Nested objects cannot be form encoded"
+        );
+        assert!(encode(&json!("a=b"), BodyEncoding::Form).is_err());
+
+        let bytes = encode(&json!("AAEC/w=="), BodyEncoding::Base64).unwrap();
+        assert_eq!(
+            bytes,
+            [0x00, 0x01, 0x02, 0xff],
+            "Raw bytes survive the round trip"
+        );
+        assert!(encode(&json!("not base64!"), BodyEncoding::Base64).is_err());
+
+        assert_eq!(
+            encode(&json!("<ping/>"), BodyEncoding::Text).unwrap(),
+            b"<ping/>"
+        );
+        assert!(encode(&json!({"a": 1}), BodyEncoding::Text).is_err());
+        assert_eq!(
+            encode(&json!({"a": 1}), BodyEncoding::Json).unwrap(),
+            br#"{"a":1}"#
+        );
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let body = json!({"a": 1});
+        assert_eq!(
+            fingerprint(Some(&body), BodyEncoding::Json, None),
+            r#"{"a":1}"#
+        );
+        assert_eq!(
+            fingerprint(Some(&body), BodyEncoding::Json, Some("Application/JSON")),
+            fingerprint(Some(&body), BodyEncoding::Json, None),
+            "The default content type spelled out is the same request"
+        );
+        assert_eq!(fingerprint(None, BodyEncoding::Text, None), "");
+        assert_eq!(
+            fingerprint(Some(&json!(null)), BodyEncoding::Json, None),
+            ""
+        );
+
+        let text = json!("<ping/>");
+        assert_ne!(
+            fingerprint(Some(&text), BodyEncoding::Text, None),
+            fingerprint(Some(&text), BodyEncoding::Json, None),
+            "The same value sent as text and as JSON is a different request"
+        );
+        assert_ne!(
+            fingerprint(Some(&text), BodyEncoding::Text, None),
+            fingerprint(Some(&text), BodyEncoding::Text, Some("application/xml")),
+        );
+        assert_eq!(
+            fingerprint(Some(&json!("AAEC/w==")), BodyEncoding::Base64, None),
+            "base64:application/octet-stream:AAEC/w=="
+        );
+        assert_eq!(
+            fingerprint(Some(&json!({"b": "2", "a": "1"})), BodyEncoding::Form, None),
+            "form:application/x-www-form-urlencoded:a=1&b=2",
+            "Field order does not change the fingerprint"
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Distinct job connect timeouts that keep a client of their own. Jobs beyond
+/// that get a new client per attempt.
+const MAX_CACHED_CLIENTS: usize = 16;
+
+/// HTTP clients shared by every job. reqwest only limits connecting per client,
+/// so jobs that set `connect_timeout_ms` share a client per distinct value.
+#[derive(Clone)]
+pub struct HttpClients {
+    default: reqwest::Client,
+    by_connect_timeout: Arc<Mutex<HashMap<Duration, reqwest::Client>>>,
+}
+
+impl HttpClients {
+    pub fn new(connect_timeout: Duration) -> Result<Self, reqwest::Error> {
+        Ok(Self {
+            default: build(connect_timeout)?,
+            by_connect_timeout: Arc::default(),
+        })
+    }
+
+    /// The client for a job's own connect timeout, or the default one
+    pub fn get(
+        &self,
+        connect_timeout: Option<Duration>,
+    ) -> Result<reqwest::Client, reqwest::Error> {
+        let Some(connect_timeout) = connect_timeout else {
+            return Ok(self.default.clone());
+        };
+
+        let mut clients = self.by_connect_timeout.lock().unwrap();
+        if let Some(client) = clients.get(&connect_timeout) {
+            return Ok(client.clone());
+        }
+        let client = build(connect_timeout)?;
+        if clients.len() < MAX_CACHED_CLIENTS {
+            clients.insert(connect_timeout, client.clone());
+        }
+        Ok(client)
+    }
+}
+
+/// The total timeout is set per request
+fn build(connect_timeout: Duration) -> Result<reqwest::Client, reqwest::Error> {
+    reqwest::Client::builder()
+        .connect_timeout(connect_timeout)
+        .dns_resolver(Arc::new(SystemResolver))
+        .build()
+}
+
+/// A host name that could not be resolved. It stays in the source chain of the
+/// request error, so failures can be told apart from connection errors.
+#[derive(Debug)]
+pub struct DnsError(std::io::Error);
+
+impl std::fmt::Display for DnsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("could not resolve host")
+    }
+}
+
+impl std::error::Error for DnsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+/// The system resolver, as reqwest uses by default, with its errors wrapped in `DnsError`
+struct SystemResolver;
+
+impl Resolve for SystemResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            // The port is replaced by the URL's when connecting
+            let addrs: Vec<_> = tokio::net::lookup_host((name.as_str(), 0))
+                .await
+                .map_err(DnsError)?
+                .collect();
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clients_are_shared_per_connect_timeout() {
+        let clients = HttpClients::new(Duration::from_secs(10)).unwrap();
+        let cached = || clients.by_connect_timeout.lock().unwrap().len();
+
+        clients.get(None).unwrap();
+        assert_eq!(cached(), 0, "The default client is not cached again");
+
+        clients.get(Some(Duration::from_secs(2))).unwrap();
+        clients.get(Some(Duration::from_secs(2))).unwrap();
+        assert_eq!(cached(), 1);
+
+        for ms in 1..=MAX_CACHED_CLIENTS as u64 + 4 {
+            clients.get(Some(Duration::from_millis(ms))).unwrap();
+        }
+        assert_eq!(cached(), MAX_CACHED_CLIENTS);
+    }
+}
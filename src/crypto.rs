@@ -0,0 +1,80 @@
+use aes_gcm::aead::{Aead, Payload};
+use aes_gcm::{Aes256Gcm, Key, KeyInit, Nonce};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+
+const NONCE_LEN: usize = 12;
+
+/// A 256-bit AES-GCM key
+#[derive(Clone)]
+pub struct SecretKey(Key<Aes256Gcm>);
+
+impl std::fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SecretKey(..)")
+    }
+}
+
+impl SecretKey {
+    /// Reads a base64 encoded 32 byte key
+    pub fn from_base64(encoded: &str) -> Result<Self, String> {
+        let bytes = STANDARD
+            .decode(encoded.trim())
+            .map_err(|e| format!("key is not base64: {}", e))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// A new random key
+    pub fn generate() -> Self {
+        let bytes: [u8; 32] = rand::random();
+        Self(bytes.into())
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() != 32 {
+            return Err(format!("key must be 32 bytes, got {}", bytes.len()));
+        }
+        Ok(Self(*Key::<Aes256Gcm>::from_slice(bytes)))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.0.as_slice()
+    }
+
+    /// Encrypts `plaintext` under a fresh nonce. `aad` is authenticated but not stored,
+    /// so the same `aad` must be passed to `open`.
+    pub fn seal(&self, plaintext: &[u8], aad: &[u8]) -> String {
+        let nonce: [u8; NONCE_LEN] = rand::random();
+        let ciphertext = Aes256Gcm::new(&self.0)
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .expect("AES-GCM encryption cannot fail for in-memory buffers");
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        STANDARD.encode(sealed)
+    }
+
+    pub fn open(&self, sealed: &str, aad: &[u8]) -> Result<Vec<u8>, String> {
+        let sealed = STANDARD
+            .decode(sealed)
+            .map_err(|e| format!("ciphertext is not base64: {}", e))?;
+        if sealed.len() < NONCE_LEN {
+            return Err("ciphertext is too short".to_string());
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        Aes256Gcm::new(&self.0)
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
+            .map_err(|_| "decryption failed, wrong key or tampered data".to_string())
+    }
+}
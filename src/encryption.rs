@@ -0,0 +1,421 @@
+use crate::crypto::SecretKey;
+use crate::entity::job;
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, Condition, DatabaseConnection, EntityTrait, QueryFilter,
+    QueryOrder, QuerySelect,
+};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+
+/// Rows re-encrypted per query by `badger encryption rotate`
+const ROTATE_BATCH_SIZE: u64 = 500;
+
+const SIGNING_SECRET_FIELD: &str = "signing_secret";
+
+/// Master keys for job payloads, by key id.
+///
+/// Each job gets its own random data key, which encrypts `headers`, `body` and
+/// `signing_secret` and is stored wrapped by the active master key. Rotating only rewraps the data keys, so
+/// retired master keys can be removed once `badger encryption rotate` has run.
+#[derive(Debug, Clone, Default)]
+pub struct Keyring {
+    active: Option<String>,
+    keys: HashMap<String, SecretKey>,
+}
+
+impl Keyring {
+    /// Reads `ENCRYPTION_KEYS` and `ENCRYPTION_KEY_ID`. Without keys payloads are stored
+    /// in plain JSON, as before.
+    pub fn from_env() -> Result<Self, String> {
+        let keys = std::env::var("ENCRYPTION_KEYS").unwrap_or_default();
+        let active = std::env::var("ENCRYPTION_KEY_ID").ok();
+        Self::parse(&keys, active.as_deref())
+    }
+
+    /// Parses `id=key` pairs separated by commas, keys being base64 32 byte AES keys.
+    /// New rows use `active`, or the first key listed.
+    pub fn parse(list: &str, active: Option<&str>) -> Result<Self, String> {
+        let mut keyring = Self::default();
+        for entry in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            // The entry is not echoed, it may be a bare key, whose base64 padding
+            // also splits on '='
+            let (id, key) = entry
+                .split_once('=')
+                .filter(|(_, key)| !key.trim().is_empty())
+                .ok_or("expected id=key pairs in ENCRYPTION_KEYS")?;
+            let id = id.trim().to_string();
+            let key = SecretKey::from_base64(key).map_err(|e| format!("key {}: {}", id, e))?;
+            if keyring.keys.insert(id.clone(), key).is_some() {
+                return Err(format!("key {} is listed twice", id));
+            }
+            keyring.active.get_or_insert(id);
+        }
+        if let Some(active) = active {
+            if !keyring.keys.contains_key(active) {
+                return Err(format!("active key {} is not in ENCRYPTION_KEYS", active));
+            }
+            keyring.active = Some(active.to_string());
+        }
+        Ok(keyring)
+    }
+
+    pub fn active_key_id(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+
+    fn key(&self, id: &str) -> Result<&SecretKey, String> {
+        self.keys
+            .get(id)
+            .ok_or_else(|| format!("encryption key {} is not configured", id))
+    }
+
+    /// Stores `headers`, `body` and `signing_secret` on the row, encrypted under a new
+    /// data key when a master key is configured
+    pub fn encrypt(
+        &self,
+        active: &mut job::ActiveModel,
+        headers: JsonValue,
+        body: JsonValue,
+        signing_secret: Option<String>,
+    ) {
+        let Some(key_id) = &self.active else {
+            active.headers = Set(headers);
+            active.body = Set(body);
+            active.signing_secret = Set(signing_secret);
+            active.encryption_key_id = Set(None);
+            active.encrypted_data_key = Set(None);
+            return;
+        };
+
+        let data_key = SecretKey::generate();
+        active.headers = Set(seal_json(&data_key, &headers, "headers"));
+        active.body = Set(seal_json(&data_key, &body, "body"));
+        active.signing_secret = Set(signing_secret
+            .map(|secret| data_key.seal(secret.as_bytes(), SIGNING_SECRET_FIELD.as_bytes())));
+        active.encryption_key_id = Set(Some(key_id.clone()));
+        active.encrypted_data_key = Set(Some(self.wrap(key_id, &data_key)));
+    }
+
+    /// The job with its `headers` and `body` in plain JSON and its `signing_secret` in
+    /// plain text. Rows stored without encryption are returned as they are.
+    pub fn decrypt(&self, mut job: job::Model) -> Result<job::Model, String> {
+        let Some(data_key) = self.data_key(&job)? else {
+            return Ok(job);
+        };
+        job.headers = open_json(&data_key, &job.headers, "headers")?;
+        job.body = open_json(&data_key, &job.body, "body")?;
+        job.signing_secret = job
+            .signing_secret
+            .map(|sealed| open_text(&data_key, &sealed, SIGNING_SECRET_FIELD))
+            .transpose()?;
+        Ok(job)
+    }
+
+    fn data_key(&self, job: &job::Model) -> Result<Option<SecretKey>, String> {
+        let (Some(key_id), Some(wrapped)) = (&job.encryption_key_id, &job.encrypted_data_key)
+        else {
+            return Ok(None);
+        };
+        // The key id is bound to the wrapped key, so a row cannot claim another key
+        let bytes = self.key(key_id)?.open(wrapped, key_id.as_bytes())?;
+        SecretKey::from_bytes(&bytes).map(Some)
+    }
+
+    fn wrap(&self, key_id: &str, data_key: &SecretKey) -> String {
+        let key = self
+            .key(key_id)
+            .expect("the active key is always configured");
+        key.seal(data_key.as_bytes(), key_id.as_bytes())
+    }
+
+    /// The columns that move `job` to the active master key, keeping its data key.
+    /// Rows stored in plain JSON are encrypted.
+    fn rotate(&self, job: &job::Model) -> Result<job::ActiveModel, String> {
+        let key_id = self
+            .active
+            .as_ref()
+            .ok_or("rotating needs ENCRYPTION_KEYS")?;
+        let mut active = job::ActiveModel::default();
+        match self.data_key(job)? {
+            Some(data_key) => {
+                active.encryption_key_id = Set(Some(key_id.clone()));
+                active.encrypted_data_key = Set(Some(self.wrap(key_id, &data_key)));
+            }
+            None => self.encrypt(
+                &mut active,
+                job.headers.clone(),
+                job.body.clone(),
+                job.signing_secret.clone(),
+            ),
+        }
+        Ok(active)
+    }
+}
+
+/// Encrypted JSON is stored as a JSON string, so the column type does not change
+fn seal_json(key: &SecretKey, value: &JsonValue, field: &str) -> JsonValue {
+    JsonValue::String(key.seal(value.to_string().as_bytes(), field.as_bytes()))
+}
+
+fn open_json(key: &SecretKey, value: &JsonValue, field: &str) -> Result<JsonValue, String> {
+    let sealed = value
+        .as_str()
+        .ok_or_else(|| format!("encrypted {} is not a string", field))?;
+    let plaintext = key
+        .open(sealed, field.as_bytes())
+        .map_err(|e| format!("{}: {}", field, e))?;
+    serde_json::from_slice(&plaintext).map_err(|e| format!("{}: {}", field, e))
+}
+
+fn open_text(key: &SecretKey, sealed: &str, field: &str) -> Result<String, String> {
+    let plaintext = key
+        .open(sealed, field.as_bytes())
+        .map_err(|e| format!("{}: {}", field, e))?;
+    String::from_utf8(plaintext).map_err(|e| format!("{}: {}", field, e))
+}
+
+/// Moves every job that is not on the active key to it. Jobs changed by the API
+/// while this runs are skipped, they are already saved under the active key.
+async fn rotate_all(keyring: &Keyring, db: &DatabaseConnection) -> Result<(), String> {
+    let key_id = keyring
+        .active_key_id()
+        .ok_or("rotating needs ENCRYPTION_KEYS")?
+        .to_string();
+    let (mut rotated, mut skipped, mut failed) = (0u64, 0u64, 0u64);
+    let mut after: Option<uuid::Uuid> = None;
+
+    loop {
+        let mut select = job::Entity::find().filter(
+            Condition::any()
+                .add(job::Column::EncryptionKeyId.is_null())
+                .add(job::Column::EncryptionKeyId.ne(key_id.as_str())),
+        );
+        if let Some(id) = after {
+            select = select.filter(job::Column::Id.gt(id));
+        }
+        let jobs = select
+            .order_by_asc(job::Column::Id)
+            .limit(ROTATE_BATCH_SIZE)
+            .all(db)
+            .await
+            .map_err(|e| e.to_string())?;
+        let Some(last) = jobs.last() else {
+            break;
+        };
+        after = Some(last.id);
+
+        for job in jobs {
+            let active = match keyring.rotate(&job) {
+                Ok(active) => active,
+                Err(e) => {
+                    eprintln!("job {}: {}", job.id, e);
+                    failed += 1;
+                    continue;
+                }
+            };
+            // Only if the row still holds the data key it was read with
+            let unchanged = match &job.encrypted_data_key {
+                Some(wrapped) => job::Column::EncryptedDataKey.eq(wrapped.as_str()),
+                None => job::Column::EncryptedDataKey.is_null(),
+            };
+            let result = job::Entity::update_many()
+                .set(active)
+                .filter(job::Column::Id.eq(job.id))
+                .filter(unchanged)
+                .exec(db)
+                .await
+                .map_err(|e| e.to_string())?;
+            if result.rows_affected == 0 {
+                skipped += 1;
+            } else {
+                rotated += 1;
+            }
+        }
+    }
+
+    println!(
+        "moved {} jobs to key {}, {} changed meanwhile, {} failed",
+        rotated, key_id, skipped, failed
+    );
+    if failed > 0 {
+        return Err(format!("{} jobs could not be re-encrypted", failed));
+    }
+    Ok(())
+}
+
+/// Handles `badger encryption rotate`
+pub async fn run_command(args: &[String], db: &DatabaseConnection) -> Result<(), String> {
+    match args {
+        [action] if action == "rotate" => rotate_all(&Keyring::from_env()?, db).await,
+        _ => Err("usage: badger encryption rotate".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+    use serde_json::json;
+
+    fn key() -> String {
+        STANDARD.encode(rand::random::<[u8; 32]>())
+    }
+
+    const SIGNING_SECRET: &str = "whsec_MfKQ9r8GKYqrTwjUPD8ILPZIo2LaLaSw";
+
+    /// A job with `headers`, `body` and a signing secret stored the way `keyring`
+    /// stores them
+    fn stored_job(keyring: &Keyring, headers: JsonValue, body: JsonValue) -> job::Model {
+        let mut active = job::ActiveModel::default();
+        keyring.encrypt(&mut active, headers, body, Some(SIGNING_SECRET.to_string()));
+        let now = chrono::Utc::now().naive_utc();
+        job::Model {
+            unique_id: "fingerprint".to_string(),
+            id: uuid::Uuid::new_v4(),
+            url: "http://a.test/".to_string(),
+            method: "POST".to_string(),
+            headers: active.headers.unwrap(),
+            body: active.body.unwrap(),
+            retries: 0,
+            attempts: 0,
+            status: crate::entity::sea_orm_active_enums::StatusEnum::Pending,
+            next_run_at: now,
+            created_at: now,
+            updated_at: now,
+            check_in: None,
+            cron: None,
+            host: None,
+            max_attempts: None,
+            response_status: None,
+            response_headers: None,
+            response_body: None,
+            response_truncated: None,
+            response_length: None,
+            timeout_ms: None,
+            retry_policy: None,
+            failure_reason: None,
+            body_encoding: None,
+            content_type: None,
+            signing_secret: active.signing_secret.unwrap(),
+            encryption_key_id: active.encryption_key_id.unwrap(),
+            encrypted_data_key: active.encrypted_data_key.unwrap(),
+            priority: 0,
+            queue: "default".to_string(),
+            scheduled_at: None,
+            connect_timeout_ms: None,
+        }
+    }
+
+    #[test]
+    fn test_payload_round_trip() {
+        let headers = json!({"Authorization": "Bearer token"});
+        let body = json!({"amount": 42});
+
+        let keyring = Keyring::parse(&format!("2026-10={}", key()), None).unwrap();
+        let job = stored_job(&keyring, headers.clone(), body.clone());
+        assert_eq!(job.encryption_key_id.as_deref(), Some("2026-10"));
+        assert!(job.headers.is_string(), "Headers are stored sealed");
+        assert!(!job.headers.to_string().contains("Bearer"));
+        assert_ne!(job.signing_secret.as_deref(), Some(SIGNING_SECRET));
+        let job = keyring.decrypt(job).unwrap();
+        assert_eq!((job.headers, job.body), (headers.clone(), body.clone()));
+        assert_eq!(job.signing_secret.as_deref(), Some(SIGNING_SECRET));
+
+        // Without keys payloads are stored and read back as they are
+        let plain = stored_job(&Keyring::default(), headers.clone(), body.clone());
+        assert_eq!(plain.encryption_key_id, None);
+        assert_eq!(plain.headers, headers);
+        assert_eq!(plain.signing_secret.as_deref(), Some(SIGNING_SECRET));
+        assert_eq!(keyring.decrypt(plain).unwrap().body, body);
+    }
+
+    #[test]
+    fn test_rotation_rewraps_the_data_key() {
+        let (old, new) = (key(), key());
+        let headers = json!({"Authorization": "Bearer token"});
+        let old_keyring = Keyring::parse(&format!("2026-01={}", old), None).unwrap();
+        let mut job = stored_job(&old_keyring, headers.clone(), json!(null));
+
+        let keyring = Keyring::parse(&format!("2026-10={},2026-01={}", new, old), None).unwrap();
+        let rotated = keyring.rotate(&job).unwrap();
+        assert!(
+            rotated.headers.is_not_set()
+                && rotated.body.is_not_set()
+                && rotated.signing_secret.is_not_set(),
+            "The payload ciphertext stays as it is"
+        );
+        job.encryption_key_id = rotated.encryption_key_id.unwrap();
+        job.encrypted_data_key = rotated.encrypted_data_key.unwrap();
+        assert_eq!(job.encryption_key_id.as_deref(), Some("2026-10"));
+
+        // The old key can be removed once every job has moved
+        let new_only = Keyring::parse(&format!("2026-10={}", new), None).unwrap();
+        assert_eq!(new_only.decrypt(job.clone()).unwrap().headers, headers);
+        assert!(old_keyring.decrypt(job).is_err());
+
+        // Rows stored in plain JSON are encrypted
+        let plain = stored_job(&Keyring::default(), headers.clone(), json!(null));
+        let rotated = keyring.rotate(&plain).unwrap();
+        assert!(rotated.headers.unwrap().is_string());
+        assert_ne!(
+            rotated.signing_secret.unwrap().as_deref(),
+            Some(SIGNING_SECRET)
+        );
+        assert!(Keyring::default().rotate(&plain).is_err());
+    }
+
+    #[test]
+    fn test_sealed_fields_are_bound_to_their_place() {
+        let key = key();
+        let keyring = Keyring::parse(&format!("a={},b={}", key, key), None).unwrap();
+        let job = stored_job(&keyring, json!({"x": 1}), json!({"y": 2}));
+
+        let swapped = job::Model {
+            headers: job.body.clone(),
+            body: job.headers.clone(),
+            ..job.clone()
+        };
+        assert!(
+            keyring.decrypt(swapped).is_err(),
+            "Headers cannot be read back as the body"
+        );
+
+        // Even under the same key material, a row cannot claim another key id
+        let relabelled = job::Model {
+            encryption_key_id: Some("b".to_string()),
+            ..job
+        };
+        assert!(keyring.decrypt(relabelled).is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let key = key();
+        let error = |list: &str, active| Keyring::parse(list, active).unwrap_err();
+
+        let missing_id = error(&key, None);
+        assert!(missing_id.contains("id=key"));
+        assert!(!missing_id.contains(&key), "A bare key is not echoed");
+        assert!(error("a=not-a-key", None).starts_with("key a:"));
+        assert!(error(&format!("a={}", STANDARD.encode([0u8; 16])), None).contains("32 bytes"));
+        assert_eq!(
+            error(&format!("a={},a={}", key, key), None),
+            "key a is listed twice"
+        );
+        assert_eq!(
+            error(&format!("a={}", key), Some("b")),
+            "active key b is not in ENCRYPTION_KEYS"
+        );
+
+        let keyring = Keyring::parse(&format!(" a={} , b={} ,", key, key), None).unwrap();
+        assert_eq!(
+            keyring.active_key_id(),
+            Some("a"),
+            "The first key is active"
+        );
+        let keyring = Keyring::parse(&format!("a={},b={}", key, key), Some("b")).unwrap();
+        assert_eq!(keyring.active_key_id(), Some("b"));
+        assert_eq!(Keyring::parse("", None).unwrap().active_key_id(), None);
+    }
+}
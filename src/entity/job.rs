@@ -1,8 +1,7 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.19
 
-use super::sea_orm_active_enums::StatusEnum;
+use super::sea_orm_active_enums::{BodyEncoding, FailureReason, StatusEnum};
 use sea_orm::entity::prelude::*;
-use serde_json::Value as JsonValue;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, serde::Serialize)]
 #[sea_orm(table_name = "job")]
@@ -22,6 +21,29 @@ pub struct Model {
     pub updated_at: DateTime,
     pub check_in: Option<DateTime>,
     pub cron: Option<String>,
+    pub host: Option<String>,
+    pub max_attempts: Option<i32>,
+    pub response_status: Option<i32>,
+    pub response_headers: Option<Json>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub response_body: Option<String>,
+    pub response_truncated: Option<bool>,
+    pub response_length: Option<i64>,
+    pub timeout_ms: Option<i32>,
+    pub retry_policy: Option<Json>,
+    pub failure_reason: Option<FailureReason>,
+    pub body_encoding: Option<BodyEncoding>,
+    pub content_type: Option<String>,
+    #[serde(skip_serializing)]
+    pub signing_secret: Option<String>,
+    #[serde(skip_serializing)]
+    pub encryption_key_id: Option<String>,
+    #[serde(skip_serializing)]
+    pub encrypted_data_key: Option<String>,
+    pub priority: i32,
+    pub queue: String,
+    pub scheduled_at: Option<DateTime>,
+    pub connect_timeout_ms: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
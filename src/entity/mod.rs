@@ -3,4 +3,6 @@
 pub mod prelude;
 
 pub mod job;
+pub mod job_attempt;
+pub mod queue;
 pub mod sea_orm_active_enums;
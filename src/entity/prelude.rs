@@ -1,3 +1,7 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.19
 
+#![allow(unused_imports)]
+
 pub use super::job::Entity as Job;
+pub use super::job_attempt::Entity as JobAttempt;
+pub use super::queue::Entity as Queue;
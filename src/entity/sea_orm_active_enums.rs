@@ -2,7 +2,9 @@
 
 use sea_orm::entity::prelude::*;
 
-#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, serde::Serialize)]
+#[derive(
+    Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, serde::Serialize, serde::Deserialize,
+)]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "status_enum")]
 pub enum StatusEnum {
     #[sea_orm(string_value = "Pending")]
@@ -13,4 +15,92 @@ pub enum StatusEnum {
     Success,
     #[sea_orm(string_value = "Failure")]
     Failure,
+    #[sea_orm(string_value = "Cancelled")]
+    Cancelled,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "snake_case")]
+pub enum FailureReason {
+    #[sea_orm(string_value = "dns")]
+    Dns,
+    #[sea_orm(string_value = "connect")]
+    Connect,
+    #[sea_orm(string_value = "tls")]
+    Tls,
+    #[sea_orm(string_value = "timeout")]
+    Timeout,
+    /// The response body could not be read to the end. Its status still decides
+    /// whether the attempt failed.
+    #[sea_orm(string_value = "body_read")]
+    BodyRead,
+    #[sea_orm(string_value = "http_status")]
+    HttpStatus,
+    #[sea_orm(string_value = "secret")]
+    Secret,
+    #[sea_orm(string_value = "decryption")]
+    Decryption,
+}
+
+impl FailureReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Dns => "dns",
+            Self::Connect => "connect",
+            Self::Tls => "tls",
+            Self::Timeout => "timeout",
+            Self::BodyRead => "body_read",
+            Self::HttpStatus => "http_status",
+            Self::Secret => "secret",
+            Self::Decryption => "decryption",
+        }
+    }
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "snake_case")]
+pub enum BodyEncoding {
+    #[default]
+    #[sea_orm(string_value = "json")]
+    Json,
+    #[sea_orm(string_value = "text")]
+    Text,
+    #[sea_orm(string_value = "form")]
+    Form,
+    #[sea_orm(string_value = "base64")]
+    Base64,
+}
+
+impl BodyEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Text => "text",
+            Self::Form => "form",
+            Self::Base64 => "base64",
+        }
+    }
 }
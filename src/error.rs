@@ -0,0 +1,301 @@
+use axum::body::Bytes;
+use axum::extract::{FromRequest, FromRequestParts, OptionalFromRequest, Request};
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use sea_orm::DbErr;
+use serde::de::DeserializeOwned;
+
+/// Body of every error returned by the API, always wrapped as `{"error": {...}}`
+#[derive(Debug, serde::Serialize)]
+pub struct ErrorBody {
+    pub code: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldError>,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl ErrorBody {
+    pub fn validation(fields: Vec<FieldError>) -> Self {
+        Self {
+            code: "validation_failed",
+            message: "request contains invalid fields".to_string(),
+            fields,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    body: ErrorBody,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: ErrorBody {
+                code,
+                message: message.into(),
+                fields: Vec::new(),
+            },
+        }
+    }
+
+    pub fn validation(fields: Vec<FieldError>) -> Self {
+        Self {
+            status: StatusCode::UNPROCESSABLE_ENTITY,
+            body: ErrorBody::validation(fields),
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, "not_found", message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, "conflict", message)
+    }
+
+    pub fn internal() -> Self {
+        Self::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal_error",
+            "internal server error",
+        )
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            axum::Json(serde_json::json!({ "error": self.body })),
+        )
+            .into_response()
+    }
+}
+
+impl From<DbErr> for ApiError {
+    fn from(e: DbErr) -> Self {
+        tracing::error!("Database error: {}", e);
+        Self::internal()
+    }
+}
+
+fn json_content_type(headers: &HeaderMap) -> bool {
+    let Some(content_type) = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+    else {
+        return false;
+    };
+
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    mime == "application/json" || (mime.starts_with("application/") && mime.ends_with("+json"))
+}
+
+/// Like `axum::Json`, but rejects with the API error envelope and reports
+/// which field failed to deserialize
+pub struct ApiJson<T>(pub T);
+
+impl<T> ApiJson<T>
+where
+    T: DeserializeOwned,
+{
+    fn from_bytes(bytes: &[u8]) -> Result<Self, ApiError> {
+        let de = &mut serde_json::Deserializer::from_slice(bytes);
+
+        let value = serde_path_to_error::deserialize(&mut *de).map_err(|err| {
+            let path = err.path().to_string();
+            let inner = err.into_inner();
+            let message = inner.to_string();
+
+            // serde reports a missing field against its parent, so pull the name out of the message
+            let missing = message
+                .strip_prefix("missing field `")
+                .and_then(|rest| rest.split('`').next())
+                .map(|name| match path.as_str() {
+                    "." => name.to_string(),
+                    parent => format!("{}.{}", parent, name),
+                });
+
+            if let Some(field) = missing {
+                ApiError::validation(vec![FieldError::new(field, message)])
+            } else if inner.is_data() && path != "." {
+                ApiError::validation(vec![FieldError::new(path, message)])
+            } else if inner.is_data() {
+                ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, "invalid_body", message)
+            } else {
+                ApiError::new(StatusCode::BAD_REQUEST, "invalid_json", inner.to_string())
+            }
+        })?;
+
+        de.end()
+            .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, "invalid_json", e.to_string()))?;
+
+        Ok(ApiJson(value))
+    }
+}
+
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        if !json_content_type(req.headers()) {
+            return Err(ApiError::new(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "unsupported_media_type",
+                "expected request with `Content-Type: application/json`",
+            ));
+        }
+
+        let bytes = Bytes::from_request(req, state)
+            .await
+            .map_err(|e| ApiError::new(e.status(), "invalid_body", e.body_text()))?;
+
+        Self::from_bytes(&bytes)
+    }
+}
+
+impl<T, S> OptionalFromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Option<Self>, Self::Rejection> {
+        if req.headers().get(header::CONTENT_TYPE).is_none() {
+            return Ok(None);
+        }
+
+        <Self as FromRequest<S>>::from_request(req, state)
+            .await
+            .map(Some)
+    }
+}
+
+/// `axum::extract::Query` with rejections in the API error envelope
+pub struct ApiQuery<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for ApiQuery<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        axum::extract::Query::<T>::from_request_parts(parts, state)
+            .await
+            .map(|axum::extract::Query(value)| ApiQuery(value))
+            .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, "invalid_query", e.body_text()))
+    }
+}
+
+/// `axum::extract::Path` with rejections in the API error envelope
+pub struct ApiPath<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for ApiPath<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        axum::extract::Path::<T>::from_request_parts(parts, state)
+            .await
+            .map(|axum::extract::Path(value)| ApiPath(value))
+            .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, "invalid_path", e.body_text()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, serde::Deserialize)]
+    struct Child {
+        name: String,
+        count: u32,
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    struct Parent {
+        parent: Child,
+    }
+
+    fn rejection(body: &str) -> ApiError {
+        ApiJson::<Parent>::from_bytes(body.as_bytes())
+            .err()
+            .expect("The body should be rejected")
+    }
+
+    fn fields(error: &ApiError) -> Vec<&str> {
+        error.body.fields.iter().map(|f| f.field.as_str()).collect()
+    }
+
+    #[test]
+    fn test_field_errors_name_their_path() {
+        let missing = rejection(r#"{"parent": {"count": 1}}"#);
+        assert_eq!(missing.status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(missing.body.code, "validation_failed");
+        assert_eq!(fields(&missing), ["parent.name"]);
+        assert_eq!(fields(&rejection("{}")), ["parent"]);
+
+        let wrong_type = rejection(r#"{"parent": {"name": "a", "count": "many"}}"#);
+        assert_eq!(wrong_type.status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(wrong_type.body.code, "validation_failed");
+        assert_eq!(fields(&wrong_type), ["parent.count"]);
+    }
+
+    #[test]
+    fn test_body_errors() {
+        let trailing = rejection(r#"{"parent": {"name": "a", "count": 1}} garbage"#);
+        assert_eq!(trailing.status, StatusCode::BAD_REQUEST);
+        assert_eq!(trailing.body.code, "invalid_json");
+
+        let syntax = rejection(r#"{"parent": "#);
+        assert_eq!(syntax.status, StatusCode::BAD_REQUEST);
+        assert_eq!(syntax.body.code, "invalid_json");
+
+        let whole = rejection(r#""a string""#);
+        assert_eq!(whole.status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(whole.body.code, "invalid_body");
+        assert!(whole.body.fields.is_empty());
+
+        let ApiJson(Parent { parent }) =
+            ApiJson::<Parent>::from_bytes(br#"{"parent": {"name": "a", "count": 1}}"#)
+                .ok()
+                .unwrap();
+        assert_eq!((parent.name.as_str(), parent.count), ("a", 1));
+    }
+}
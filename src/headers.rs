@@ -0,0 +1,162 @@
+use crate::error::FieldError;
+use reqwest::header::{HeaderName, HeaderValue};
+use serde_json::Value as JsonValue;
+
+/// The text sent for one header value. Numbers and booleans are sent as written.
+fn value_text(value: &JsonValue) -> Option<String> {
+    match value {
+        JsonValue::String(s) => Some(s.clone()),
+        JsonValue::Number(n) => Some(n.to_string()),
+        JsonValue::Bool(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// A header's values, an array sends the header once per element
+fn values(value: &JsonValue) -> Vec<&JsonValue> {
+    match value {
+        JsonValue::Array(items) => items.iter().collect(),
+        value => vec![value],
+    }
+}
+
+/// Checks that every header can actually be sent
+pub fn validate(headers: &JsonValue, errors: &mut Vec<FieldError>) {
+    let Some(map) = headers.as_object() else {
+        errors.push(FieldError::new("headers", "headers must be a JSON object"));
+        return;
+    };
+
+    for (name, value) in map {
+        let field = format!("headers.{}", name);
+        if HeaderName::from_bytes(name.as_bytes()).is_err() {
+            errors.push(FieldError::new(
+                field,
+                format!("invalid header name: {:?}", name),
+            ));
+            continue;
+        }
+        if value.as_array().is_some_and(Vec::is_empty) {
+            errors.push(FieldError::new(field, "header has no values"));
+            continue;
+        }
+        for value in values(value) {
+            match value_text(value) {
+                Some(text) if HeaderValue::from_str(&text).is_ok() => {
+                    crate::secrets::validate(&text, &field, errors)
+                }
+                Some(text) => errors.push(FieldError::new(
+                    field.clone(),
+                    format!("invalid header value: {:?}", text),
+                )),
+                None => errors.push(FieldError::new(
+                    field.clone(),
+                    "header values must be strings, numbers, booleans or an array of them",
+                )),
+            }
+        }
+    }
+}
+
+/// Every `(name, value)` pair to send, repeated names once per value
+pub fn pairs(headers: &JsonValue) -> Vec<(&str, String)> {
+    let Some(map) = headers.as_object() else {
+        return Vec::new();
+    };
+    map.iter()
+        .flat_map(|(name, value)| {
+            values(value)
+                .into_iter()
+                .filter_map(move |value| value_text(value).map(|text| (name.as_str(), text)))
+        })
+        .collect()
+}
+
+/// Headers as they go into the fingerprint: names lowercased and sorted, values kept
+/// in the order they are sent. A header with one value is written as a string.
+pub fn fingerprint(headers: Option<&JsonValue>) -> String {
+    let mut merged: Vec<(String, Vec<String>)> = Vec::new();
+    for (name, text) in headers.map(pairs).unwrap_or_default() {
+        let name = name.to_lowercase();
+        match merged.iter_mut().find(|(existing, _)| *existing == name) {
+            Some((_, texts)) => texts.push(text),
+            None => merged.push((name, vec![text])),
+        }
+    }
+    merged.sort_by(|a, b| a.0.cmp(&b.0));
+
+    merged
+        .into_iter()
+        .map(|(name, texts)| {
+            let value = match texts.as_slice() {
+                [single] => JsonValue::from(single.as_str()),
+                _ => JsonValue::from(texts),
+            };
+            format!("{}:{}", name, value)
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_pairs() {
+        let headers = json!({"Cookie": ["a=1", "b=2"], "X-Retry": 3, "X-Debug": true});
+        assert_eq!(
+            pairs(&headers),
+            [
+                ("Cookie", "a=1".to_string()),
+                ("Cookie", "b=2".to_string()),
+                ("X-Debug", "true".to_string()),
+                ("X-Retry", "3".to_string()),
+            ]
+        );
+        assert!(pairs(&json!(null)).is_empty());
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let headers = json!({"Cookie": ["a=1", "b=2"], "X-Retry": 3, "X-Debug": true});
+        assert_eq!(
+            fingerprint(Some(&json!({"Authorization": "Bearer token"}))),
+            r#"authorization:"Bearer token""#
+        );
+        assert_eq!(
+            fingerprint(Some(&json!({"X-Id": ["1"]}))),
+            fingerprint(Some(&json!({"X-Id": "1"})))
+        );
+        assert_eq!(
+            fingerprint(Some(&json!({"X-Id": 1}))),
+            fingerprint(Some(&json!({"x-id": "1"})))
+        );
+        assert_eq!(
+            fingerprint(Some(&headers)),
+            r#"cookie:["a=1","b=2"], x-debug:"true", x-retry:"3""#
+        );
+        assert_ne!(
+            fingerprint(Some(&json!({"Link": ["<a>", "<b>"]}))),
+            fingerprint(Some(&json!({"Link": ["<b>", "<a>"]}))),
+            "Value order is part of the request"
+        );
+        assert_eq!(fingerprint(None), fingerprint(Some(&json!({}))));
+    }
+
+    #[test]
+    fn test_validate() {
+        let fields = |headers: JsonValue| {
+            let mut errors = Vec::new();
+            validate(&headers, &mut errors);
+            errors.into_iter().map(|e| e.field).collect::<Vec<_>>()
+        };
+        assert!(fields(json!({"X-Good": ["1", 2, false]})).is_empty());
+        assert_eq!(fields(json!(["X-Good"])), ["headers"]);
+        assert_eq!(fields(json!({"Bad Header": "1"})), ["headers.Bad Header"]);
+        assert_eq!(fields(json!({"X-Line": "line\nbreak"})), ["headers.X-Line"]);
+        assert_eq!(fields(json!({"X-Empty": []})), ["headers.X-Empty"]);
+        assert_eq!(fields(json!({"X-Nested": {"a": 1}})), ["headers.X-Nested"]);
+    }
+}
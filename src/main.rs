@@ -1,4 +1,12 @@
-use crate::entity::job;
+use crate::clients::HttpClients;
+use crate::encryption::Keyring;
+use crate::entity::sea_orm_active_enums::{BodyEncoding, FailureReason};
+use crate::entity::{job, job_attempt, queue};
+use crate::error::{ApiError, ApiJson, ApiPath, ApiQuery, ErrorBody, FieldError};
+use crate::retry::{BackoffStrategy, RetryConfig, RetryPolicy};
+use crate::secrets::{SecretHosts, SecretProvider};
+use crate::shutdown::Shutdown;
+use crate::signing::SigningConfig;
 use axum::extract::State;
 use axum::routing::get;
 use axum::{Router, routing::post};
@@ -7,18 +15,15 @@ use cron::Schedule;
 use dotenvy::dotenv;
 use governor::clock::Clock;
 use governor::clock::DefaultClock;
-use governor::clock::QuantaInstant;
 use governor::state::keyed::DefaultKeyedStateStore;
-use governor::state::{InMemoryState, NotKeyed};
-use governor::{DefaultKeyedRateLimiter, NotUntil, Quota, RateLimiter};
+use governor::{DefaultKeyedRateLimiter, Quota, RateLimiter};
 use metrics_exporter_prometheus::PrometheusBuilder;
-use rand::{Rng, RngExt};
-use reqwest::{Method, RequestBuilder};
+use rand::RngExt;
 use sea_orm::entity::prelude::*;
-use sea_orm::sea_query::{Expr, LockBehavior, LockType, expr};
+use sea_orm::sea_query::{LockBehavior, LockType, OnConflict, SimpleExpr};
 use sea_orm::{
-    ActiveModelTrait, ActiveValue, Condition, IntoActiveModel, QueryFilter, QueryOrder,
-    QuerySelect, Set, TransactionTrait,
+    ActiveModelTrait, Condition, DbBackend, IntoActiveModel, Order, QueryFilter, QueryOrder,
+    QuerySelect, QueryTrait, Set, TransactionTrait, TryInsertResult,
 };
 use serde_json::Value as JsonValue;
 use sha2::{Digest, Sha256};
@@ -27,10 +32,23 @@ use std::num::NonZeroU32;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Instant;
-use tracing::{Instrument, error, info, info_span};
+use tracing::{Instrument, info, info_span};
 use url::Url;
 
+mod body;
+mod clients;
+mod crypto;
+mod encryption;
 mod entity;
+mod error;
+mod headers;
+mod queues;
+mod response;
+mod retry;
+mod secrets;
+mod shutdown;
+mod signing;
+mod wakeup;
 
 type JobRateLimiter = DefaultKeyedRateLimiter<String>;
 
@@ -38,6 +56,87 @@ type JobRateLimiter = DefaultKeyedRateLimiter<String>;
 struct AppState {
     db: sea_orm::DatabaseConnection,
     limiter: std::sync::Arc<JobRateLimiter>,
+    /// Encrypts job headers and bodies at rest
+    keyring: std::sync::Arc<Keyring>,
+    /// Where each `{{secret:NAME}}` may be sent, checked on submit and at dispatch
+    secret_hosts: std::sync::Arc<SecretHosts>,
+    /// Signalled by `wakeup::listen_task` when a job becomes ready
+    wakeup: std::sync::Arc<tokio::sync::Notify>,
+}
+
+const DEFAULT_PAGE_SIZE: u64 = 50;
+const MAX_PAGE_SIZE: u64 = 500;
+const MAX_BATCH_SIZE: usize = 1000;
+const BATCH_INSERT_CHUNK_SIZE: usize = 500;
+const PURGE_BATCH_SIZE: u64 = 500;
+/// Response bodies longer than this are truncated in the attempt history
+const ATTEMPT_BODY_LIMIT: usize = 4096;
+const MAX_TIMEOUT_MS: i32 = 3_600_000;
+/// Job priorities range from `-MAX_PRIORITY` to `MAX_PRIORITY`, higher runs first
+const MAX_PRIORITY: i32 = 100;
+/// Finished jobs written back per transaction, at most
+const COMPLETION_BATCH_SIZE: usize = 100;
+/// How long past its request timeout a Running job may go without finishing
+/// before `monitor_task` considers its worker dead
+const STALE_GRACE: Duration = Duration::seconds(10);
+
+/// Execution settings shared by every worker
+#[derive(Debug, Clone)]
+struct WorkerConfig {
+    /// Server default retry policy
+    retry: RetryConfig,
+    /// Total time for a request when the job does not set `timeout_ms`
+    request_timeout: std::time::Duration,
+    /// Time to connect when the job does not set `connect_timeout_ms`
+    connect_timeout: std::time::Duration,
+    /// Response bytes kept per attempt, the rest of the body is not read
+    response_body_limit: usize,
+    /// Jobs claimed per statement, at most
+    claim_batch_size: usize,
+    /// Waiting time worth one priority level. When set, a due job gains a level
+    /// for every interval it waits, so lower priorities cannot starve.
+    priority_aging: Option<std::time::Duration>,
+    signing: SigningConfig,
+    /// Resolves `{{secret:NAME}}` placeholders in URLs and headers
+    secrets: Arc<dyn SecretProvider>,
+}
+
+impl WorkerConfig {
+    fn request_timeout(&self, job: &job::Model) -> std::time::Duration {
+        job.timeout_ms
+            .map(|ms| std::time::Duration::from_millis(ms as u64))
+            .unwrap_or(self.request_timeout)
+    }
+
+    /// `None` when the job uses the default connect timeout
+    fn connect_timeout(job: &job::Model) -> Option<std::time::Duration> {
+        job.connect_timeout_ms
+            .map(|ms| std::time::Duration::from_millis(ms as u64))
+    }
+}
+
+/// How long finished jobs are kept before `retention_task` deletes them.
+/// Statuses without a TTL are kept forever.
+#[derive(Debug, Clone)]
+struct RetentionConfig {
+    ttls: Vec<(entity::sea_orm_active_enums::StatusEnum, Duration)>,
+    batch_size: u64,
+    interval: std::time::Duration,
+}
+
+#[derive(serde::Serialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+enum BatchItemResult {
+    Created { id: Uuid },
+    Deduplicated { id: Uuid },
+    Invalid { error: ErrorBody },
+}
+
+#[derive(serde::Serialize)]
+struct CreateJobResponse {
+    id: Uuid,
+    status: entity::sea_orm_active_enums::StatusEnum,
+    deduplicated: bool,
 }
 
 #[derive(serde::Deserialize)]
@@ -48,6 +147,238 @@ struct JobRequest {
     body: Option<JsonValue>,
     run_at: Option<chrono::DateTime<Utc>>,
     cron: Option<String>,
+    timeout_ms: Option<i32>,
+    /// Limits connecting, `CONNECT_TIMEOUT_MS` when unset
+    connect_timeout_ms: Option<i32>,
+    retry: Option<RetryPolicy>,
+    /// How `body` is sent, JSON when unset
+    body_encoding: Option<BodyEncoding>,
+    /// Overrides the encoding's default `Content-Type`
+    content_type: Option<String>,
+    /// Signs deliveries with this secret instead of the host or global one
+    signing_secret: Option<String>,
+    /// Higher priorities are claimed first, 0 when unset
+    priority: Option<i32>,
+    /// Only processes serving this queue run the job, `default` when unset
+    queue: Option<String>,
+}
+
+/// Filters shared by `GET /jobs` and the bulk endpoints
+#[derive(serde::Deserialize, Default, PartialEq)]
+struct JobFilter {
+    status: Option<entity::sea_orm_active_enums::StatusEnum>,
+    host: Option<String>,
+    method: Option<String>,
+    cron: Option<bool>,
+    created_after: Option<chrono::DateTime<Utc>>,
+    created_before: Option<chrono::DateTime<Utc>>,
+    updated_after: Option<chrono::DateTime<Utc>>,
+    updated_before: Option<chrono::DateTime<Utc>>,
+    next_run_after: Option<chrono::DateTime<Utc>>,
+    next_run_before: Option<chrono::DateTime<Utc>>,
+    failure_reason: Option<FailureReason>,
+    queue: Option<String>,
+}
+
+impl JobFilter {
+    /// Matches every job
+    fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn condition(&self) -> Condition {
+        let mut cond = Condition::all();
+
+        if let Some(status) = &self.status {
+            cond = cond.add(job::Column::Status.eq(status.clone()));
+        }
+        if let Some(host) = &self.host {
+            cond = cond.add(job::Column::Host.eq(host.to_lowercase()));
+        }
+        if let Some(method) = &self.method {
+            cond = cond.add(job::Column::Method.eq(method.to_uppercase()));
+        }
+        match self.cron {
+            Some(true) => cond = cond.add(job::Column::Cron.is_not_null()),
+            Some(false) => cond = cond.add(job::Column::Cron.is_null()),
+            None => {}
+        }
+        if let Some(t) = self.created_after {
+            cond = cond.add(job::Column::CreatedAt.gte(t.naive_utc()));
+        }
+        if let Some(t) = self.created_before {
+            cond = cond.add(job::Column::CreatedAt.lt(t.naive_utc()));
+        }
+        if let Some(t) = self.updated_after {
+            cond = cond.add(job::Column::UpdatedAt.gte(t.naive_utc()));
+        }
+        if let Some(t) = self.updated_before {
+            cond = cond.add(job::Column::UpdatedAt.lt(t.naive_utc()));
+        }
+        if let Some(t) = self.next_run_after {
+            cond = cond.add(job::Column::NextRunAt.gte(t.naive_utc()));
+        }
+        if let Some(t) = self.next_run_before {
+            cond = cond.add(job::Column::NextRunAt.lt(t.naive_utc()));
+        }
+        if let Some(reason) = self.failure_reason {
+            cond = cond.add(job::Column::FailureReason.eq(reason));
+        }
+        if let Some(queue) = &self.queue {
+            cond = cond.add(job::Column::Queue.eq(queue.clone()));
+        }
+
+        cond
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct PageQuery {
+    limit: Option<u64>,
+    cursor: Option<String>,
+}
+
+#[derive(serde::Deserialize, Default)]
+struct RetryRequest {
+    run_at: Option<chrono::DateTime<Utc>>,
+    max_attempts: Option<i32>,
+}
+
+#[derive(serde::Deserialize)]
+struct PurgeRequest {
+    #[serde(flatten)]
+    filter: JobFilter,
+    /// Required to purge without any filter
+    #[serde(default)]
+    all: bool,
+}
+
+#[derive(serde::Deserialize)]
+struct BulkRetryRequest {
+    #[serde(flatten)]
+    filter: JobFilter,
+    run_at: Option<chrono::DateTime<Utc>>,
+    max_attempts: Option<i32>,
+}
+
+/// Partial update of a Pending job. Absent fields are left unchanged, an explicit
+/// `null` clears `headers`, `body`, `cron`, `timeout_ms`, `connect_timeout_ms` or `retry`.
+#[derive(serde::Deserialize)]
+struct UpdateJobRequest {
+    url: Option<String>,
+    #[serde(default, deserialize_with = "nullable")]
+    headers: Option<Option<JsonValue>>,
+    #[serde(default, deserialize_with = "nullable")]
+    body: Option<Option<JsonValue>>,
+    run_at: Option<chrono::DateTime<Utc>>,
+    #[serde(default, deserialize_with = "nullable")]
+    cron: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    timeout_ms: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "nullable")]
+    connect_timeout_ms: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "nullable")]
+    retry: Option<Option<RetryPolicy>>,
+    #[serde(default, deserialize_with = "nullable")]
+    body_encoding: Option<Option<BodyEncoding>>,
+    #[serde(default, deserialize_with = "nullable")]
+    content_type: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    signing_secret: Option<Option<String>>,
+    priority: Option<i32>,
+    queue: Option<String>,
+    /// Recompute the fingerprint from the updated job instead of keeping the original one
+    #[serde(default)]
+    rekey: bool,
+}
+
+// Tells an explicit `null` (Some(None)) apart from a missing field (None)
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::Deserialize<'de>,
+{
+    <Option<T> as serde::Deserialize>::deserialize(deserializer).map(Some)
+}
+
+#[derive(serde::Serialize)]
+struct BulkRetryResponse {
+    retried: u64,
+}
+
+#[derive(serde::Serialize)]
+struct QueueSummary {
+    name: String,
+    paused: bool,
+    /// Pending jobs, including those scheduled for later
+    pending: i64,
+    running: i64,
+}
+
+impl QueueSummary {
+    fn new(name: String, paused: bool) -> Self {
+        Self {
+            name,
+            paused,
+            pending: 0,
+            running: 0,
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct QueueList {
+    queues: Vec<QueueSummary>,
+}
+
+#[derive(serde::Serialize)]
+struct PurgeResponse {
+    purged: u64,
+}
+
+#[derive(serde::Serialize)]
+struct JobPage {
+    jobs: Vec<job::Model>,
+    next_cursor: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+struct AttemptPage {
+    attempts: Vec<job_attempt::Model>,
+    next_cursor: Option<String>,
+}
+
+/// What a worker observed while executing one attempt
+#[derive(Clone)]
+struct AttemptOutcome {
+    status: Option<reqwest::StatusCode>,
+    headers: Option<JsonValue>,
+    body: String,
+    /// `body` stops at the capture limit
+    truncated: bool,
+    /// Full size of the response body, when known
+    length: Option<i64>,
+    error: Option<String>,
+    /// Why the attempt failed, `None` when it succeeded
+    failure_reason: Option<FailureReason>,
+    /// Delay requested by a 429 or 503 response
+    retry_after: Option<Duration>,
+}
+
+impl AttemptOutcome {
+    /// An attempt that failed before anything was sent
+    fn not_sent(error: String, reason: FailureReason) -> Self {
+        Self {
+            status: None,
+            headers: None,
+            body: String::new(),
+            truncated: false,
+            length: None,
+            error: Some(error),
+            failure_reason: Some(reason),
+            retry_after: None,
+        }
+    }
 }
 
 fn create_fingerprint(
@@ -55,29 +386,19 @@ fn create_fingerprint(
     url: String,
     headers: Option<JsonValue>,
     body: Option<JsonValue>,
+    body_encoding: Option<BodyEncoding>,
+    content_type: Option<&str>,
     run_at: Option<chrono::DateTime<Utc>>,
 ) -> String {
-    // if headers are present, extract headers - authorization, content-type, idempotency-key(if present)
-    // convert keys to lowercase
-    // then sort the headers by key and store them in a vec
-    let mut headers_vec = Vec::new();
-    if let Some(headers) = headers {
-        let headers = headers.as_object().unwrap();
-        for (key, value) in headers {
-            headers_vec.push((key.to_lowercase(), value.clone()));
-        }
-        headers_vec.sort_by(|a, b| a.0.cmp(&b.0));
-    }
-
-    // create a string representation of the header, ex: key1:val1, key2:val2
-    let headers_str = headers_vec
-        .iter()
-        .map(|(key, value)| format!("{}:{}", key, value))
-        .collect::<Vec<String>>()
-        .join(", ");
+    // create a string representation of the headers, ex: key1:val1, key2:["a","b"]
+    let headers_str = headers::fingerprint(headers.as_ref());
 
     // if body is present convert to string
-    let body_str = body.map(|body| body.to_string()).unwrap_or_default();
+    let body_str = body::fingerprint(
+        body.as_ref(),
+        body_encoding.unwrap_or_default(),
+        content_type,
+    );
 
     let run_ts: i64 = run_at.map(|t| t.timestamp()).unwrap_or(0);
 
@@ -88,56 +409,228 @@ fn create_fingerprint(
     let mut hasher = Sha256::new();
     hasher.update(fingerprint);
     let hash = hasher.finalize();
-    let hash_str = hex::encode(hash);
-
-    hash_str
+    hex::encode(hash)
 }
 
-async fn create_job(
-    State(state): State<AppState>,
-    axum::Json(payload): axum::Json<JobRequest>,
-) -> Result<String, axum::http::StatusCode> {
-    let now = Utc::now().naive_utc();
+/// Checks a job request for problems that would otherwise only surface once a worker runs it
+fn validate_job_request(
+    payload: &JobRequest,
+    secret_hosts: &SecretHosts,
+) -> Result<(), Vec<FieldError>> {
+    let mut errors = Vec::new();
+
+    if reqwest::Method::from_bytes(payload.method.as_bytes()).is_err() {
+        errors.push(FieldError::new(
+            "method",
+            format!("invalid HTTP method: {:?}", payload.method),
+        ));
+    }
+
+    secrets::validate(&payload.url, "url", &mut errors);
+    // Placeholders are checked as if they held a plain value, and must not change the host
+    match Url::parse(&secrets::mask(&payload.url, "secret")) {
+        Ok(_) if url_host(&payload.url) != url_host(&secrets::mask(&payload.url, "other")) => {
+            errors.push(FieldError::new(
+                "url",
+                "secrets cannot be used in the URL host",
+            ))
+        }
+        Ok(url) if !matches!(url.scheme(), "http" | "https") => errors.push(FieldError::new(
+            "url",
+            format!("unsupported URL scheme: {}", url.scheme()),
+        )),
+        Ok(url) if url.host_str().is_none() => {
+            errors.push(FieldError::new("url", "URL has no host"))
+        }
+        Ok(_) => validate_secret_hosts(payload, secret_hosts, &mut errors),
+        Err(e) => errors.push(FieldError::new("url", format!("invalid URL: {}", e))),
+    }
+
+    if let Some(headers) = &payload.headers {
+        headers::validate(headers, &mut errors);
+    }
+
+    if let Some(exp) = &payload.cron {
+        match Schedule::from_str(exp) {
+            // A schedule like "0 0 0 30 2 *" parses but never fires
+            Ok(schedule) if schedule.upcoming(Utc).next().is_none() => errors.push(
+                FieldError::new("cron", "cron expression has no upcoming run time"),
+            ),
+            Ok(_) => {}
+            Err(e) => errors.push(FieldError::new(
+                "cron",
+                format!("invalid cron expression: {}", e),
+            )),
+        }
+    }
 
-    let url = payload.url.clone();
-    let method = payload.method.clone();
-    let headers: Option<JsonValue> = payload.headers.clone();
-    let body: Option<JsonValue> = payload.body.clone();
-    let cron_exp: Option<String> = payload.cron.clone();
+    if let Some(timeout_ms) = payload.timeout_ms
+        && !(1..=MAX_TIMEOUT_MS).contains(&timeout_ms)
+    {
+        errors.push(FieldError::new(
+            "timeout_ms",
+            format!("timeout_ms must be between 1 and {}", MAX_TIMEOUT_MS),
+        ));
+    }
+
+    if let Some(connect_timeout_ms) = payload.connect_timeout_ms
+        && !(1..=MAX_TIMEOUT_MS).contains(&connect_timeout_ms)
+    {
+        errors.push(FieldError::new(
+            "connect_timeout_ms",
+            format!(
+                "connect_timeout_ms must be between 1 and {}",
+                MAX_TIMEOUT_MS
+            ),
+        ));
+    }
+
+    if let Some(priority) = payload.priority
+        && !(-MAX_PRIORITY..=MAX_PRIORITY).contains(&priority)
+    {
+        errors.push(FieldError::new(
+            "priority",
+            format!(
+                "priority must be between {} and {}",
+                -MAX_PRIORITY, MAX_PRIORITY
+            ),
+        ));
+    }
+
+    if let Some(queue) = &payload.queue {
+        queues::validate(queue, "queue", &mut errors);
+    }
+
+    if let Some(retry) = &payload.retry {
+        retry.validate(&mut errors);
+    }
+
+    if let Some(secret) = &payload.signing_secret
+        && let Err(e) = badger_webhooks::Webhook::new(secret)
+    {
+        errors.push(FieldError::new("signing_secret", e.to_string()));
+    }
+
+    body::validate(
+        payload.body.as_ref(),
+        payload.body_encoding.unwrap_or_default(),
+        payload.content_type.as_deref(),
+        &mut errors,
+    );
 
-    let run_at = if let Some(run_at) = payload.run_at {
-        Some(run_at)
+    if errors.is_empty() {
+        Ok(())
     } else {
-        Some(Utc::now())
-    };
+        Err(errors)
+    }
+}
+
+/// Checks that the URL's host may receive every secret the URL and headers reference
+fn validate_secret_hosts(
+    payload: &JobRequest,
+    secret_hosts: &SecretHosts,
+    errors: &mut Vec<FieldError>,
+) {
+    let host = url_host(&payload.url);
+    if let Err(e) = secret_hosts.check(&payload.url, host.as_deref()) {
+        errors.push(FieldError::new("url", e));
+    }
+    for (name, value) in payload
+        .headers
+        .as_ref()
+        .map(headers::pairs)
+        .unwrap_or_default()
+    {
+        if let Err(e) = secret_hosts.check(&value, host.as_deref()) {
+            errors.push(FieldError::new(format!("headers.{}", name), e));
+        }
+    }
+}
+
+/// Host of a job URL, which never depends on a secret
+fn url_host(url: &str) -> Option<String> {
+    Url::parse(&secrets::mask(url, "secret"))
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_string))
+}
+
+fn new_job_model(payload: JobRequest, now: NaiveDateTime, keyring: &Keyring) -> job::ActiveModel {
+    let url = payload.url;
+    let method = payload.method;
+    let headers: Option<JsonValue> = payload.headers;
+    let body: Option<JsonValue> = payload.body;
+    let cron_exp: Option<String> = payload.cron;
+    let retry = payload.retry.unwrap_or_default();
+
+    let run_at = payload.run_at.unwrap_or_else(Utc::now);
 
     let unique_id = create_fingerprint(
         method.clone(),
         url.clone(),
         headers.clone(),
         body.clone(),
-        run_at.clone(),
+        payload.body_encoding,
+        payload.content_type.as_deref(),
+        Some(run_at),
     );
 
-    let new_job = job::ActiveModel {
-        unique_id: Set(unique_id.clone()),
+    let host = url_host(&url);
+
+    let mut model = job::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        unique_id: Set(unique_id),
         url: Set(url),
+        status: Set(entity::sea_orm_active_enums::StatusEnum::Pending),
         method: Set(method),
-        headers: Set(headers.unwrap_or(serde_json::json!({}))),
-        body: Set(body.unwrap_or(serde_json::json!(null))),
         retries: Set(0),
         attempts: Set(0),
-        next_run_at: Set(run_at.unwrap().naive_utc()),
+        next_run_at: Set(run_at.naive_utc()),
+        scheduled_at: Set(Some(run_at.naive_utc())),
         created_at: Set(now),
         updated_at: Set(now),
         cron: Set(cron_exp),
+        host: Set(host),
+        timeout_ms: Set(payload.timeout_ms),
+        connect_timeout_ms: Set(payload.connect_timeout_ms),
+        max_attempts: Set(retry.max_attempts),
+        retry_policy: Set(retry.to_json()),
+        body_encoding: Set(payload.body_encoding),
+        content_type: Set(payload.content_type),
+        priority: Set(payload.priority.unwrap_or(0)),
+        queue: Set(payload.queue.unwrap_or_else(|| queues::DEFAULT.to_string())),
         ..Default::default()
     };
+    keyring.encrypt(
+        &mut model,
+        headers.unwrap_or(serde_json::json!({})),
+        body.unwrap_or(serde_json::json!(null)),
+        payload.signing_secret,
+    );
+    model
+}
+
+async fn create_job(
+    State(state): State<AppState>,
+    ApiJson(payload): ApiJson<JobRequest>,
+) -> Result<(axum::http::StatusCode, axum::Json<CreateJobResponse>), ApiError> {
+    validate_job_request(&payload, &state.secret_hosts).map_err(ApiError::validation)?;
+
+    let now = Utc::now().naive_utc();
+
+    let new_job = new_job_model(payload, now, &state.keyring);
+    let unique_id = new_job.unique_id.as_ref().clone();
 
     match new_job.insert(&state.db).await {
         Ok(model) => {
             // successful insert
-            Ok(model.id.to_string() + "\n")
+            Ok((
+                axum::http::StatusCode::CREATED,
+                axum::Json(CreateJobResponse {
+                    id: model.id,
+                    status: model.status,
+                    deduplicated: false,
+                }),
+            ))
         }
 
         Err(DbErr::Query(sea_orm::RuntimeErr::SqlxError(e)))
@@ -149,23 +642,109 @@ async fn create_job(
             let existing_job = job::Entity::find()
                 .filter(job::Column::UniqueId.eq(unique_id))
                 .one(&state.db)
-                .await
-                .map_err(|e| {
-                    println!("Database error: {}", e);
-                    axum::http::StatusCode::INTERNAL_SERVER_ERROR
-                })?;
+                .await?;
 
             match existing_job {
-                Some(job) => Ok(job.id.to_string() + "\n"),
-                None => Err(axum::http::StatusCode::INTERNAL_SERVER_ERROR),
+                Some(job) => Ok((
+                    axum::http::StatusCode::OK,
+                    axum::Json(CreateJobResponse {
+                        id: job.id,
+                        status: job.status,
+                        deduplicated: true,
+                    }),
+                )),
+                None => Err(ApiError::internal()),
             }
         }
 
-        Err(e) => {
-            println!("Database insertion error: {}", e);
-            Err(axum::http::StatusCode::INTERNAL_SERVER_ERROR)
+        Err(e) => Err(e.into()),
+    }
+}
+
+async fn create_jobs_batch(
+    State(state): State<AppState>,
+    ApiJson(payloads): ApiJson<Vec<JobRequest>>,
+) -> Result<axum::Json<Vec<BatchItemResult>>, ApiError> {
+    if payloads.len() > MAX_BATCH_SIZE {
+        return Err(ApiError::new(
+            axum::http::StatusCode::PAYLOAD_TOO_LARGE,
+            "batch_too_large",
+            format!("a batch may contain at most {} jobs", MAX_BATCH_SIZE),
+        ));
+    }
+
+    let now = Utc::now().naive_utc();
+    let mut results: Vec<Option<BatchItemResult>> = Vec::with_capacity(payloads.len());
+    let mut valid: Vec<(usize, job::ActiveModel)> = Vec::new();
+
+    for (index, payload) in payloads.into_iter().enumerate() {
+        match validate_job_request(&payload, &state.secret_hosts) {
+            Ok(()) => {
+                valid.push((index, new_job_model(payload, now, &state.keyring)));
+                results.push(None);
+            }
+            Err(fields) => results.push(Some(BatchItemResult::Invalid {
+                error: ErrorBody::validation(fields),
+            })),
+        }
+    }
+
+    let txn = state.db.begin().await?;
+
+    // fingerprint -> id of every job this batch resolved to, so repeats within
+    // the batch are reported as deduplicated against the first occurrence
+    let mut seen: HashMap<String, Uuid> = HashMap::new();
+
+    for chunk in valid.chunks(BATCH_INSERT_CHUNK_SIZE) {
+        let inserted = job::Entity::insert_many(chunk.iter().map(|(_, model)| model.clone()))
+            .on_conflict(
+                OnConflict::column(job::Column::UniqueId)
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .do_nothing()
+            .exec_with_returning_many(&txn)
+            .await?;
+
+        let mut created: HashMap<String, Uuid> = match inserted {
+            TryInsertResult::Inserted(models) => {
+                models.into_iter().map(|m| (m.unique_id, m.id)).collect()
+            }
+            TryInsertResult::Empty | TryInsertResult::Conflicted => HashMap::new(),
+        };
+
+        let conflicted: Vec<String> = chunk
+            .iter()
+            .map(|(_, model)| model.unique_id.as_ref().clone())
+            .filter(|fp| !created.contains_key(fp) && !seen.contains_key(fp))
+            .collect();
+
+        if !conflicted.is_empty() {
+            let existing = job::Entity::find()
+                .filter(job::Column::UniqueId.is_in(conflicted))
+                .all(&txn)
+                .await?;
+            seen.extend(existing.into_iter().map(|m| (m.unique_id, m.id)));
+        }
+
+        for (index, model) in chunk {
+            let fingerprint = model.unique_id.as_ref();
+            let result = if let Some(id) = created.remove(fingerprint) {
+                seen.insert(fingerprint.clone(), id);
+                BatchItemResult::Created { id }
+            } else if let Some(id) = seen.get(fingerprint) {
+                BatchItemResult::Deduplicated { id: *id }
+            } else {
+                tracing::error!("Job {} conflicted but could not be found", fingerprint);
+                return Err(ApiError::internal());
+            };
+            results[*index] = Some(result);
         }
     }
+
+    txn.commit().await?;
+
+    Ok(axum::Json(results.into_iter().flatten().collect()))
 }
 
 fn next_execution_time(expr: String) -> Option<chrono::DateTime<chrono::Utc>> {
@@ -175,376 +754,1711 @@ fn next_execution_time(expr: String) -> Option<chrono::DateTime<chrono::Utc>> {
             Some(next)
         }
         Err(e) => {
-            eprintln!("Invalid cron expression: {}", e);
+            tracing::error!("Invalid cron expression: {}", e);
             None
         }
     }
 }
 
+async fn find_job(db: &DatabaseConnection, id: Uuid) -> Result<job::Model, ApiError> {
+    job::Entity::find()
+        .filter(job::Column::Id.eq(id))
+        .one(db)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("job {} not found", id)))
+}
+
+/// The job with its payload decrypted, as the API returns it
+fn decrypt_job(keyring: &Keyring, job: job::Model) -> Result<job::Model, ApiError> {
+    let id = job.id;
+    keyring.decrypt(job).map_err(|e| {
+        tracing::error!("Cannot decrypt job {}: {}", id, e);
+        ApiError::internal()
+    })
+}
+
 async fn get_job(
     State(state): State<AppState>,
-    axum::extract::Path(id): axum::extract::Path<uuid::Uuid>,
-) -> Result<axum::Json<job::Model>, axum::http::StatusCode> {
-    let job = job::Entity::find_by_id(id)
-        .one(&state.db)
-        .await
-        .map_err(|e| {
-            eprintln!("Database error: {}", e);
-            axum::http::StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    ApiPath(id): ApiPath<uuid::Uuid>,
+) -> Result<axum::Json<job::Model>, ApiError> {
+    let job = find_job(&state.db, id).await?;
+    Ok(axum::Json(decrypt_job(&state.keyring, job)?))
+}
 
-    match job {
-        Some(job) => Ok(axum::Json(job)),
-        None => Err(axum::http::StatusCode::NOT_FOUND),
-    }
+// Cursors are opaque to clients: hex of "<timestamp micros>|<id>" of the last row on a page
+fn encode_cursor(at: NaiveDateTime, id: Uuid) -> String {
+    hex::encode(format!("{}|{}", at.and_utc().timestamp_micros(), id))
 }
 
-async fn worker_task(state: AppState, max_attempts: i32) {
-    // Built once, outside the loop
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .expect("Failed to build HTTP client");
+fn decode_cursor(cursor: &str) -> Option<(NaiveDateTime, Uuid)> {
+    let raw = String::from_utf8(hex::decode(cursor).ok()?).ok()?;
+    let (micros, id) = raw.split_once('|')?;
+    let created_at = chrono::DateTime::from_timestamp_micros(micros.parse().ok()?)?.naive_utc();
+    let id = Uuid::parse_str(id).ok()?;
+    Some((created_at, id))
+}
 
-    loop {
-        let now = Utc::now().naive_utc();
+async fn list_jobs(
+    State(state): State<AppState>,
+    ApiQuery(filter): ApiQuery<JobFilter>,
+    ApiQuery(page): ApiQuery<PageQuery>,
+) -> Result<axum::Json<JobPage>, ApiError> {
+    let limit = page
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let mut select = job::Entity::find().filter(filter.condition());
+
+    // Keyset pagination: continue strictly after the (created_at, id) of the previous page
+    if let Some(cursor) = page.cursor {
+        let (created_at, id) = decode_cursor(&cursor).ok_or_else(|| {
+            ApiError::new(
+                axum::http::StatusCode::BAD_REQUEST,
+                "invalid_query",
+                "invalid cursor",
+            )
+        })?;
+        select = select.filter(
+            Condition::any()
+                .add(job::Column::CreatedAt.lt(created_at))
+                .add(
+                    Condition::all()
+                        .add(job::Column::CreatedAt.eq(created_at))
+                        .add(job::Column::Id.lt(id)),
+                ),
+        );
+    }
 
-        let job_opt = (&state.db)
-            .transaction::<_, Option<job::Model>, DbErr>(|txn| {
-                Box::pin(async move {
-                    let job = job::Entity::find()
-                        .filter(
-                            job::Column::Status
-                                .eq(entity::sea_orm_active_enums::StatusEnum::Pending),
-                        )
-                        .filter(
-                            Condition::any()
-                                .add(job::Column::NextRunAt.lte(now))
-                                .add(job::Column::NextRunAt.is_null()),
-                        )
-                        .order_by_asc(job::Column::CreatedAt)
-                        .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked)
-                        .one(txn)
-                        .await?;
-
-                    if let Some(job) = job {
-                        let mut active = job.clone().into_active_model();
-
-                        active.status = Set(entity::sea_orm_active_enums::StatusEnum::Running);
-                        active.attempts = Set(job.attempts + 1);
-                        active.updated_at = Set(now);
-                        active.check_in = Set(Some(now));
-
-                        let updated = active.update(txn).await?;
-                        Ok(Some(updated))
-                    } else {
-                        Ok(None)
-                    }
-                })
-            })
-            .await
-            .unwrap_or(None);
-
-        let job = match job_opt {
-            Some(j) => j,
-            None => {
-                tracing::debug!("No pending jobs");
-                // Jitter prevents thundering herd when multiple workers are running
-                let jitter = rand::rng().random_range(0..=1000);
-                tokio::time::sleep(std::time::Duration::from_millis(5000 + jitter)).await;
-                continue;
+    // Fetch one extra row to know whether another page exists
+    let mut jobs = select
+        .order_by_desc(job::Column::CreatedAt)
+        .order_by_desc(job::Column::Id)
+        .limit(limit + 1)
+        .all(&state.db)
+        .await?;
+
+    let next_cursor = if jobs.len() as u64 > limit {
+        jobs.truncate(limit as usize);
+        jobs.last().map(|job| encode_cursor(job.created_at, job.id))
+    } else {
+        None
+    };
+    // One unreadable row should not hide the rest of the page
+    let jobs = jobs
+        .into_iter()
+        .map(|job| match state.keyring.decrypt(job.clone()) {
+            Ok(job) => job,
+            Err(e) => {
+                tracing::error!("Cannot decrypt job {}: {}", job.id, e);
+                job::Model {
+                    headers: JsonValue::Null,
+                    body: JsonValue::Null,
+                    ..job
+                }
             }
-        };
+        })
+        .collect();
 
-        let start = Instant::now();
+    Ok(axum::Json(JobPage { jobs, next_cursor }))
+}
 
-        let delta = now - job.next_run_at;
-        let lag = delta.to_std().map(|d| d.as_secs_f64()).unwrap_or(0.0);
+async fn list_job_attempts(
+    State(state): State<AppState>,
+    ApiPath(id): ApiPath<uuid::Uuid>,
+    ApiQuery(page): ApiQuery<PageQuery>,
+) -> Result<axum::Json<AttemptPage>, ApiError> {
+    // 404 for unknown jobs rather than an empty history
+    find_job(&state.db, id).await?;
+
+    let limit = page
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+
+    let mut select = job_attempt::Entity::find().filter(job_attempt::Column::JobId.eq(id));
+
+    if let Some(cursor) = page.cursor {
+        let (started_at, attempt_id) = decode_cursor(&cursor).ok_or_else(|| {
+            ApiError::new(
+                axum::http::StatusCode::BAD_REQUEST,
+                "invalid_query",
+                "invalid cursor",
+            )
+        })?;
+        select = select.filter(
+            Condition::any()
+                .add(job_attempt::Column::StartedAt.lt(started_at))
+                .add(
+                    Condition::all()
+                        .add(job_attempt::Column::StartedAt.eq(started_at))
+                        .add(job_attempt::Column::Id.lt(attempt_id)),
+                ),
+        );
+    }
 
-        metrics::histogram!("job_queue_lag_seconds").record(lag);
+    let mut attempts = select
+        .order_by_desc(job_attempt::Column::StartedAt)
+        .order_by_desc(job_attempt::Column::Id)
+        .limit(limit + 1)
+        .all(&state.db)
+        .await?;
+
+    let next_cursor = if attempts.len() as u64 > limit {
+        attempts.truncate(limit as usize);
+        attempts
+            .last()
+            .map(|attempt| encode_cursor(attempt.started_at, attempt.id))
+    } else {
+        None
+    };
 
-        async {
-            info!("Job picked up");
+    Ok(axum::Json(AttemptPage {
+        attempts,
+        next_cursor,
+    }))
+}
 
-            let method = match reqwest::Method::from_bytes(job.method.as_bytes()) {
-                Ok(m) => m,
-                Err(_) => {
-                    tracing::error!("Invalid HTTP method for job {}", job.id);
-                    // Mark as Failure — otherwise the job is stuck in Running forever
-                    let mut active = job.clone().into_active_model();
-                    active.status = Set(entity::sea_orm_active_enums::StatusEnum::Failure);
-                    active.updated_at = Set(Utc::now().naive_utc());
-                    if let Err(e) = active.update(&state.db as &DatabaseConnection).await {
-                        tracing::error!("Failed to mark job {} as failed: {}", job.id, e);
-                    }
-                    return;
-                }
-            };
+/// Every queue that has jobs waiting or running, or has been paused or resumed
+async fn list_queues(State(state): State<AppState>) -> Result<axum::Json<QueueList>, ApiError> {
+    use entity::sea_orm_active_enums::StatusEnum;
+
+    let mut queues: std::collections::BTreeMap<String, QueueSummary> = queue::Entity::find()
+        .all(&state.db)
+        .await?
+        .into_iter()
+        .map(|q| (q.name.clone(), QueueSummary::new(q.name, q.paused)))
+        .collect();
+
+    let counts: Vec<(String, StatusEnum, i64)> = job::Entity::find()
+        .select_only()
+        .column(job::Column::Queue)
+        .column(job::Column::Status)
+        .column_as(job::Column::Id.count(), "count")
+        .filter(job::Column::Status.is_in([StatusEnum::Pending, StatusEnum::Running]))
+        .group_by(job::Column::Queue)
+        .group_by(job::Column::Status)
+        .into_tuple()
+        .all(&state.db)
+        .await?;
+
+    for (name, status, count) in counts {
+        let summary = queues
+            .entry(name.clone())
+            .or_insert_with(|| QueueSummary::new(name, false));
+        match status {
+            StatusEnum::Pending => summary.pending = count,
+            _ => summary.running = count,
+        }
+    }
 
-            let url = match Url::parse(&job.url) {
-                Ok(u) => u,
-                Err(e) => {
-                    tracing::error!("Failed to parse URL for job {}: {}", job.id, e);
-                    // Mark as Failure — otherwise the job is stuck in Running forever
-                    let mut active = job.clone().into_active_model();
-                    active.status = Set(entity::sea_orm_active_enums::StatusEnum::Failure);
-                    active.updated_at = Set(Utc::now().naive_utc());
-                    if let Err(e) = active.update(&state.db as &DatabaseConnection).await {
-                        tracing::error!("Failed to mark job {} as failed: {}", job.id, e);
-                    }
-                    return;
-                }
-            };
+    Ok(axum::Json(QueueList {
+        queues: queues.into_values().collect(),
+    }))
+}
 
-            let limiter = state.limiter.clone();
-
-            let go_ahead = (&state.db)
-                .transaction::<_, bool, DbErr>(|txn| {
-                    let job = job.clone();
-                    let url = url.clone();
-                    Box::pin(async move {
-                        let mut active = job.clone().into_active_model();
-
-                        if let Some(host) = url.host_str() {
-                            match limiter.check_key(&host.to_string()) {
-                                Ok(_) => {
-                                    // Rate limit not hit — proceed
-                                    return Ok(true);
-                                }
-                                Err(nbd) => {
-                                    let now = limiter.clock().now();
-                                    let wait_dur = nbd.wait_time_from(now);
-                                    let next_available_utc =
-                                        Utc::now() + chrono::Duration::from_std(wait_dur).unwrap();
-
-                                    // Roll back the attempt/retry increment from pick-up
-                                    let attempts = (job.attempts - 1).max(0);
-                                    active.status =
-                                        Set(entity::sea_orm_active_enums::StatusEnum::Pending);
-                                    active.updated_at = Set(Utc::now().naive_utc());
-                                    active.next_run_at = Set(next_available_utc.naive_utc());
-                                    active.attempts = Set(attempts);
-                                    active.retries = Set((attempts - 1).max(0));
-
-                                    tracing::warn!(
-                                        "Rate limited for host {}, next available at {}",
-                                        host,
-                                        next_available_utc
-                                    );
-                                }
-                            }
-                        } else {
-                            tracing::error!("Job {} has no valid host in URL {}", job.id, url);
-                            active.status = Set(entity::sea_orm_active_enums::StatusEnum::Failure);
-                            active.updated_at = Set(Utc::now().naive_utc());
-                            // Roll back attempt increment — this was a bad job, not a real attempt
-                            let attempts = (job.attempts - 1).max(0);
-                            active.attempts = Set(attempts);
-                            active.retries = Set((attempts - 1).max(0));
-                        }
-
-                        active.update(txn).await?;
-                        Ok(false)
-                    })
-                })
-                .await
-                .unwrap_or(false);
+/// Stops every process from claiming the queue's jobs. Running jobs still finish.
+async fn pause_queue(
+    State(state): State<AppState>,
+    ApiPath(name): ApiPath<String>,
+) -> Result<axum::Json<queue::Model>, ApiError> {
+    validate_queue_name(&name)?;
+    let queue = queues::set_paused(&state.db, &name, true).await?;
+    info!("Queue {} paused", name);
+    Ok(axum::Json(queue))
+}
 
-            if !go_ahead {
-                return;
-            }
+async fn resume_queue(
+    State(state): State<AppState>,
+    ApiPath(name): ApiPath<String>,
+) -> Result<axum::Json<queue::Model>, ApiError> {
+    validate_queue_name(&name)?;
+    let queue = queues::set_paused(&state.db, &name, false).await?;
+    info!("Queue {} resumed", name);
+
+    // Its due jobs are ready now, in this process and every other one
+    state.wakeup.notify_waiters();
+    if let Err(e) = wakeup::notify(&state.db).await {
+        tracing::error!("Failed to wake workers after resuming {}: {}", name, e);
+    }
 
-            let mut request = client.request(method, &job.url);
+    Ok(axum::Json(queue))
+}
 
-            if let Some(map) = job.headers.as_object() {
-                for (k, v) in map {
-                    if let Some(val) = v.as_str() {
-                        request = request.header(k, val);
-                    }
-                }
-            }
+fn validate_queue_name(name: &str) -> Result<(), ApiError> {
+    let mut errors = Vec::new();
+    queues::validate(name, "name", &mut errors);
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::validation(errors))
+    }
+}
 
-            if !job.body.is_null() {
-                request = request.json(&job.body);
-            }
+async fn cancel_job(
+    State(state): State<AppState>,
+    ApiPath(id): ApiPath<uuid::Uuid>,
+) -> Result<axum::Json<job::Model>, ApiError> {
+    use entity::sea_orm_active_enums::StatusEnum;
+
+    // A Running job is cancelled in place: the worker finishes its request but
+    // will not overwrite the status or reschedule the cron
+    let cancelled = job::Entity::update_many()
+        .set(job::ActiveModel {
+            status: Set(StatusEnum::Cancelled),
+            updated_at: Set(Utc::now().naive_utc()),
+            ..Default::default()
+        })
+        .filter(job::Column::Id.eq(id))
+        .filter(job::Column::Status.is_in([StatusEnum::Pending, StatusEnum::Running]))
+        .exec(&state.db)
+        .await?;
+
+    let job = find_job(&state.db, id).await?;
+
+    // Cancelling twice is fine, cancelling a finished job is not
+    if cancelled.rows_affected == 0 && job.status != StatusEnum::Cancelled {
+        return Err(ApiError::conflict(format!(
+            "job {} is {:?} and can no longer be cancelled",
+            id, job.status
+        )));
+    }
 
-            let (status, response_body) = match request.send().await {
-                Ok(resp) => {
-                    let status = resp.status();
-                    let text = resp.text().await.unwrap_or_default();
-                    (status, text)
-                }
-                Err(e) => {
-                    tracing::error!("HTTP error for job {}: {}", job.id, e);
-                    (reqwest::StatusCode::INTERNAL_SERVER_ERROR, String::new())
-                }
-            };
+    Ok(axum::Json(decrypt_job(&state.keyring, job)?))
+}
 
-            if let Err(e) = (&state.db)
-                .transaction::<_, (), DbErr>(|txn| {
-                    let job = job.clone();
-                    let response_body = response_body.clone();
-                    Box::pin(async move {
-                        let mut active = job.clone().into_active_model();
-
-                        if status.is_success() {
-                            let cron_exp = job.cron.clone();
-
-                            match cron_exp {
-                                Some(exp) => {
-                                    let next_time = next_execution_time(exp.clone());
-
-                                    if let Some(dt) = next_time {
-                                        active.status =
-                                            Set(entity::sea_orm_active_enums::StatusEnum::Pending);
-                                        active.next_run_at = Set(dt.naive_utc());
-                                        active.attempts = Set(0);
-                                        active.retries = Set(0);
-                                    } else {
-                                        active.status =
-                                            Set(entity::sea_orm_active_enums::StatusEnum::Failure);
-                                        tracing::error!(
-                                            "Cron expression for job {} is invalid: {}",
-                                            job.id,
-                                            exp
-                                        );
-                                    }
-                                }
-                                None => {
-                                    active.status =
-                                        Set(entity::sea_orm_active_enums::StatusEnum::Success);
-                                }
-                            }
-
-                            active.updated_at = Set(Utc::now().naive_utc());
-                            active.retries = Set((job.attempts - 1).max(0));
-
-                            let json: serde_json::Value =
-                                serde_json::from_str(&response_body).unwrap_or(JsonValue::Null);
-                            active.body = Set(json);
-
-                            metrics::counter!("job_execution_result", "status" => "success")
-                                .increment(1);
-                        } else {
-                            let attempts = job.attempts;
-                            active.retries = Set((attempts - 1).max(0));
-
-                            if attempts >= max_attempts {
-                                active.status =
-                                    Set(entity::sea_orm_active_enums::StatusEnum::Failure);
-                            } else {
-                                active.status =
-                                    Set(entity::sea_orm_active_enums::StatusEnum::Pending);
-
-                                let exp = attempts.max(0) as u32;
-                                let mut backoff = 1000 * 2i64.pow(exp);
-                                let jitter: i64 = rand::rng().random_range(-500..=500);
-                                backoff = (backoff + jitter).max(0);
-
-                                let next_time =
-                                    (Utc::now() + Duration::milliseconds(backoff)).naive_utc();
-                                active.next_run_at = Set(next_time);
-                            }
-
-                            active.updated_at = Set(Utc::now().naive_utc());
-
-                            metrics::counter!("job_execution_result", "status" => "failure")
-                                .increment(1);
-                        }
-
-                        active.update(txn).await?;
-                        Ok(())
-                    })
-                })
-                .await
-            {
-                tracing::error!("Failed to update job {} after execution: {}", job.id, e);
-            }
+async fn update_job(
+    State(state): State<AppState>,
+    ApiPath(id): ApiPath<uuid::Uuid>,
+    ApiJson(payload): ApiJson<UpdateJobRequest>,
+) -> Result<axum::Json<job::Model>, ApiError> {
+    use entity::sea_orm_active_enums::StatusEnum;
+
+    let txn = state.db.begin().await?;
+
+    // Hold the row so a worker cannot claim it while it is being changed
+    let job = job::Entity::find()
+        .filter(job::Column::Id.eq(id))
+        .lock_exclusive()
+        .one(&txn)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("job {} not found", id)))?;
+    let job = decrypt_job(&state.keyring, job)?;
+
+    match job.status {
+        StatusEnum::Pending => {}
+        StatusEnum::Running => {
+            return Err(ApiError::conflict(format!(
+                "job {} is Running and cannot be updated until it finishes",
+                id
+            )));
         }
-        .instrument(info_span!("Processing job", job_id = %job.id))
-        .await;
+        status => {
+            return Err(ApiError::conflict(format!(
+                "job {} is {:?}, only Pending jobs can be updated",
+                id, status
+            )));
+        }
+    }
 
-        let duration = start.elapsed().as_secs_f64();
+    let merged = JobRequest {
+        url: payload.url.unwrap_or_else(|| job.url.clone()),
+        method: job.method.clone(),
+        headers: payload.headers.unwrap_or_else(|| Some(job.headers.clone())),
+        // A stored null body fingerprints the same as a request without one
+        body: payload
+            .body
+            .unwrap_or_else(|| Some(job.body.clone()))
+            .filter(|body| !body.is_null()),
+        run_at: Some(payload.run_at.unwrap_or_else(|| job.next_run_at.and_utc())),
+        cron: payload.cron.unwrap_or_else(|| job.cron.clone()),
+        timeout_ms: payload.timeout_ms.unwrap_or(job.timeout_ms),
+        connect_timeout_ms: payload.connect_timeout_ms.unwrap_or(job.connect_timeout_ms),
+        retry: payload
+            .retry
+            .unwrap_or_else(|| Some(RetryPolicy::from_job(&job))),
+        body_encoding: payload.body_encoding.unwrap_or(job.body_encoding),
+        content_type: payload
+            .content_type
+            .unwrap_or_else(|| job.content_type.clone()),
+        signing_secret: payload
+            .signing_secret
+            .unwrap_or_else(|| job.signing_secret.clone()),
+        priority: Some(payload.priority.unwrap_or(job.priority)),
+        queue: Some(payload.queue.unwrap_or_else(|| job.queue.clone())),
+    };
+    validate_job_request(&merged, &state.secret_hosts).map_err(ApiError::validation)?;
 
-        metrics::histogram!("job_execution_duration_seconds").record(duration);
+    let unique_id = create_fingerprint(
+        merged.method.clone(),
+        merged.url.clone(),
+        merged.headers.clone(),
+        merged.body.clone(),
+        merged.body_encoding,
+        merged.content_type.as_deref(),
+        merged.run_at,
+    );
+    let rekey = payload.rekey && unique_id != job.unique_id;
+
+    let mut active = job.clone().into_active_model();
+    active.host = Set(url_host(&merged.url));
+    active.url = Set(merged.url);
+    // Saved under a new data key and the active master key
+    state.keyring.encrypt(
+        &mut active,
+        merged.headers.unwrap_or(serde_json::json!({})),
+        merged.body.unwrap_or(serde_json::json!(null)),
+        merged.signing_secret,
+    );
+    let next_run_at = merged.run_at.unwrap_or_else(Utc::now).naive_utc();
+    active.next_run_at = Set(next_run_at);
+    // A new due time starts a new run, with a new webhook-id
+    if payload.run_at.is_some() {
+        active.scheduled_at = Set(Some(next_run_at));
     }
-}
+    active.cron = Set(merged.cron);
+    active.timeout_ms = Set(merged.timeout_ms);
+    active.connect_timeout_ms = Set(merged.connect_timeout_ms);
+    let retry = merged.retry.unwrap_or_default();
+    active.max_attempts = Set(retry.max_attempts);
+    active.retry_policy = Set(retry.to_json());
+    active.body_encoding = Set(merged.body_encoding);
+    active.content_type = Set(merged.content_type);
+    active.priority = Set(merged.priority.unwrap_or(0));
+    active.queue = Set(merged.queue.unwrap_or_else(|| queues::DEFAULT.to_string()));
+    active.updated_at = Set(Utc::now().naive_utc());
+
+    let updated = if rekey {
+        if let Some(existing) = job::Entity::find()
+            .filter(job::Column::UniqueId.eq(unique_id.clone()))
+            .one(&txn)
+            .await?
+        {
+            return Err(ApiError::conflict(format!(
+                "job {} already has the fingerprint of the updated job",
+                existing.id
+            )));
+        }
 
-async fn monitor_task(state: AppState) {
-    loop {
-        let cutoff = Utc::now().naive_utc() - Duration::seconds(30);
+        // `prevent_job_unique_id_update` forbids changing the key in place, so the
+        // row is replaced under the same id within this transaction
+        job::Entity::delete_many()
+            .filter(job::Column::Id.eq(id))
+            .exec(&txn)
+            .await?;
 
-        let job = job::Entity::find()
-            .filter(
-                job::Column::Status.eq(crate::entity::sea_orm_active_enums::StatusEnum::Running),
-            )
-            .filter(
-                Condition::any().add(job::Column::CheckIn.lte(cutoff)).add(
-                    Condition::all()
-                        .add(job::Column::CheckIn.is_null())
-                        .add(job::Column::UpdatedAt.lte(cutoff)),
-                ),
-            )
-            .order_by_asc(job::Column::UpdatedAt)
-            .one(&state.db)
-            .await;
+        active.unique_id = Set(unique_id);
+        active.reset_all().insert(&txn).await?
+    } else {
+        active.update(&txn).await?
+    };
 
-        match job {
-            Ok(Some(job)) => {
-                let mut active_job = job.into_active_model();
-                active_job.check_in = Set(Some(Utc::now().naive_utc()));
-                active_job.status = Set(entity::sea_orm_active_enums::StatusEnum::Pending);
+    txn.commit().await?;
 
-                if let Err(e) = active_job.update(&state.db).await {
-                    eprintln!("Error while processing job: {}", e);
-                }
-            }
-            Ok(None) => {
-                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-            }
-            Err(e) => {
-                eprintln!("Error fetching job: {}", e);
-                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-            }
-        }
+    Ok(axum::Json(decrypt_job(&state.keyring, updated)?))
+}
+
+/// Resets the attempt counters and the last outcome, and moves the job back to
+/// Pending as a new run with its own webhook-id
+fn requeue_model(
+    run_at: Option<chrono::DateTime<Utc>>,
+    max_attempts: Option<i32>,
+) -> job::ActiveModel {
+    let now = Utc::now();
+    let next_run_at = run_at.unwrap_or(now).naive_utc();
+
+    let mut active = job::ActiveModel {
+        status: Set(entity::sea_orm_active_enums::StatusEnum::Pending),
+        attempts: Set(0),
+        retries: Set(0),
+        next_run_at: Set(next_run_at),
+        scheduled_at: Set(Some(next_run_at)),
+        updated_at: Set(now.naive_utc()),
+        check_in: Set(None),
+        failure_reason: Set(None),
+        response_status: Set(None),
+        response_headers: Set(None),
+        response_body: Set(None),
+        response_truncated: Set(None),
+        response_length: Set(None),
+        ..Default::default()
+    };
+
+    if let Some(max_attempts) = max_attempts {
+        active.max_attempts = Set(Some(max_attempts));
+    }
+
+    active
+}
+
+fn validate_max_attempts(max_attempts: Option<i32>) -> Result<(), ApiError> {
+    if max_attempts.is_some_and(|n| n < 1) {
+        return Err(ApiError::validation(vec![FieldError::new(
+            "max_attempts",
+            "max_attempts must be at least 1",
+        )]));
+    }
+    Ok(())
+}
+
+async fn retry_job(
+    State(state): State<AppState>,
+    ApiPath(id): ApiPath<uuid::Uuid>,
+    payload: Option<ApiJson<RetryRequest>>,
+) -> Result<axum::Json<job::Model>, ApiError> {
+    use entity::sea_orm_active_enums::StatusEnum;
+
+    let payload = payload.map(|ApiJson(p)| p).unwrap_or_default();
+    validate_max_attempts(payload.max_attempts)?;
+
+    let retried = job::Entity::update_many()
+        .set(requeue_model(payload.run_at, payload.max_attempts))
+        .filter(job::Column::Id.eq(id))
+        .filter(job::Column::Status.is_in([StatusEnum::Failure, StatusEnum::Cancelled]))
+        .exec(&state.db)
+        .await?;
+
+    let job = find_job(&state.db, id).await?;
+
+    // Only finished-but-unsuccessful jobs can be requeued
+    if retried.rows_affected == 0 {
+        return Err(ApiError::conflict(format!(
+            "job {} is {:?}, only Failure or Cancelled jobs can be retried",
+            id, job.status
+        )));
+    }
+
+    Ok(axum::Json(decrypt_job(&state.keyring, job)?))
+}
+
+async fn bulk_retry_jobs(
+    State(state): State<AppState>,
+    ApiJson(payload): ApiJson<BulkRetryRequest>,
+) -> Result<axum::Json<BulkRetryResponse>, ApiError> {
+    use entity::sea_orm_active_enums::StatusEnum;
+
+    validate_max_attempts(payload.max_attempts)?;
+    if payload.filter.is_empty() {
+        return Err(ApiError::validation(vec![FieldError::new(
+            "filter",
+            "give at least one filter to choose the jobs to retry",
+        )]));
+    }
+
+    // Cancelled jobs were stopped on purpose, they are only retried when asked for
+    let retried_status = match payload.filter.status {
+        Some(StatusEnum::Cancelled) => StatusEnum::Cancelled,
+        _ => StatusEnum::Failure,
+    };
+
+    let result = job::Entity::update_many()
+        .set(requeue_model(payload.run_at, payload.max_attempts))
+        .filter(payload.filter.condition())
+        .filter(job::Column::Status.eq(retried_status))
+        .exec(&state.db)
+        .await?;
+
+    info!("Requeued {} jobs", result.rows_affected);
+
+    Ok(axum::Json(BulkRetryResponse {
+        retried: result.rows_affected,
+    }))
+}
+
+async fn delete_job(
+    State(state): State<AppState>,
+    ApiPath(id): ApiPath<uuid::Uuid>,
+) -> Result<axum::http::StatusCode, ApiError> {
+    use entity::sea_orm_active_enums::StatusEnum;
+
+    let txn = state.db.begin().await?;
+
+    let deleted = job::Entity::delete_many()
+        .filter(job::Column::Id.eq(id))
+        .filter(job::Column::Status.ne(StatusEnum::Running))
+        .exec(&txn)
+        .await?;
+
+    // A Running job keeps its history, the attempt in flight still writes to it
+    if deleted.rows_affected == 0 {
+        txn.rollback().await?;
+        let job = find_job(&state.db, id).await?;
+        return Err(ApiError::conflict(format!(
+            "job {} is {:?} and cannot be deleted, cancel it first",
+            id, job.status
+        )));
+    }
+
+    job_attempt::Entity::delete_many()
+        .filter(job_attempt::Column::JobId.eq(id))
+        .exec(&txn)
+        .await?;
+
+    txn.commit().await?;
+
+    Ok(axum::http::StatusCode::NO_CONTENT)
+}
+
+/// Deletes finished jobs matching the filter
+async fn purge_jobs(
+    State(state): State<AppState>,
+    ApiJson(payload): ApiJson<PurgeRequest>,
+) -> Result<axum::Json<PurgeResponse>, ApiError> {
+    use entity::sea_orm_active_enums::StatusEnum;
+
+    // An empty body is more likely a mistake than a request to delete everything
+    if payload.filter.is_empty() && !payload.all {
+        return Err(ApiError::validation(vec![FieldError::new(
+            "all",
+            "give at least one filter, or \"all\": true to purge every finished job",
+        )]));
+    }
+
+    let condition = payload.filter.condition().add(job::Column::Status.is_in([
+        StatusEnum::Success,
+        StatusEnum::Failure,
+        StatusEnum::Cancelled,
+    ]));
+
+    let purged = delete_in_batches(&state.db, condition, PURGE_BATCH_SIZE).await?;
+    info!("Purged {} jobs", purged);
+
+    Ok(axum::Json(PurgeResponse { purged }))
+}
+
+/// Deletes the jobs matching `condition` together with their attempt history,
+/// at most `batch_size` jobs per statement so a large purge never holds locks
+/// on a big part of the table. Returns the number of deleted jobs.
+async fn delete_in_batches(
+    db: &DatabaseConnection,
+    condition: Condition,
+    batch_size: u64,
+) -> Result<u64, DbErr> {
+    let mut deleted = 0;
+
+    loop {
+        let batch = job::Entity::find()
+            .select_only()
+            .column(job::Column::Id)
+            .filter(condition.clone())
+            .limit(batch_size)
+            .into_query();
+
+        let txn = db.begin().await?;
+
+        // The condition is repeated so a row that changed since the subquery is left alone
+        let ids: Vec<Uuid> = job::Entity::delete_many()
+            .filter(job::Column::Id.in_subquery(batch))
+            .filter(condition.clone())
+            .exec_with_returning(&txn)
+            .await?
+            .into_iter()
+            .map(|job| job.id)
+            .collect();
+
+        job_attempt::Entity::delete_many()
+            .filter(job_attempt::Column::JobId.is_in(ids.clone()))
+            .exec(&txn)
+            .await?;
+
+        txn.commit().await?;
+
+        deleted += ids.len() as u64;
+        if (ids.len() as u64) < batch_size {
+            return Ok(deleted);
+        }
+    }
+}
+
+/// Writes back a job claimed by a worker, but only while it is still `Running`,
+/// so a cancellation that landed mid-flight is never overwritten.
+/// Returns whether the row was updated.
+async fn update_if_running<C: ConnectionTrait>(
+    db: &C,
+    job_id: Uuid,
+    active: job::ActiveModel,
+) -> Result<bool, DbErr> {
+    let result = job::Entity::update_many()
+        .set(active)
+        .filter(job::Column::Id.eq(job_id))
+        .filter(job::Column::Status.eq(entity::sea_orm_active_enums::StatusEnum::Running))
+        .exec(db)
+        .await?;
+
+    Ok(result.rows_affected > 0)
+}
+
+fn headers_to_json(headers: &reqwest::header::HeaderMap) -> JsonValue {
+    let mut map = serde_json::Map::new();
+    for (name, value) in headers {
+        let value = String::from_utf8_lossy(value.as_bytes()).into_owned();
+        // Repeated headers are joined the way they could be sent on one line
+        match map.get_mut(name.as_str()) {
+            Some(JsonValue::String(existing)) => {
+                existing.push_str(", ");
+                existing.push_str(&value);
+            }
+            _ => {
+                map.insert(name.as_str().to_string(), JsonValue::String(value));
+            }
+        }
+    }
+    JsonValue::Object(map)
+}
+
+/// Formats an error with its sources, since reqwest's own message rarely names the cause
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(e) = source {
+        message.push_str(": ");
+        message.push_str(&e.to_string());
+        source = e.source();
+    }
+    message
+}
+
+/// Sorts a failed `send()` into the step that failed
+fn classify_send_error(error: &reqwest::Error) -> FailureReason {
+    if error.is_timeout() {
+        return FailureReason::Timeout;
+    }
+    let mut source = std::error::Error::source(error);
+    while let Some(e) = source {
+        if e.is::<clients::DnsError>() {
+            return FailureReason::Dns;
+        }
+        if is_tls_error(e) {
+            return FailureReason::Tls;
+        }
+        source = e.source();
+    }
+    FailureReason::Connect
+}
+
+/// rustls reports handshake and certificate failures as a `rustls::Error`, which
+/// hyper-rustls wraps in an `io::Error`
+fn is_tls_error(error: &(dyn std::error::Error + 'static)) -> bool {
+    if error.is::<rustls::Error>() {
+        return true;
+    }
+    // `io::Error::source` skips the error it wraps
+    error
+        .downcast_ref::<std::io::Error>()
+        .and_then(|io| io.get_ref())
+        .is_some_and(|inner| is_tls_error(inner))
+}
+
+/// Cuts `text` to at most `limit` bytes without splitting a character
+fn truncate_utf8(text: &str, limit: usize) -> &str {
+    if text.len() <= limit {
+        return text;
+    }
+    let mut end = limit;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+fn attempt_model(
+    job: &job::Model,
+    worker_id: &str,
+    started_at: chrono::DateTime<Utc>,
+    finished_at: chrono::DateTime<Utc>,
+    outcome: &AttemptOutcome,
+) -> job_attempt::ActiveModel {
+    job_attempt::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        job_id: Set(job.id),
+        attempt: Set(job.attempts),
+        worker_id: Set(worker_id.to_string()),
+        started_at: Set(started_at.naive_utc()),
+        finished_at: Set(finished_at.naive_utc()),
+        latency_ms: Set((finished_at - started_at).num_milliseconds()),
+        response_status: Set(outcome.status.map(|s| s.as_u16() as i32)),
+        response_headers: Set(outcome.headers.clone()),
+        response_body: Set(outcome
+            .status
+            .map(|_| truncate_utf8(&outcome.body, ATTEMPT_BODY_LIMIT).to_string())),
+        response_truncated: Set(outcome
+            .status
+            .map(|_| outcome.truncated || outcome.body.len() > ATTEMPT_BODY_LIMIT)),
+        response_length: Set(outcome.length),
+        error: Set(outcome.error.clone()),
+        failure_reason: Set(outcome.failure_reason),
+    }
+}
+
+/// Identifies one run of a job, so receivers can tell a retry from the next cron run
+fn webhook_id(job: &job::Model) -> String {
+    let due = job.scheduled_at.unwrap_or(job.next_run_at);
+    format!("{}_{}", job.id, due.and_utc().timestamp())
+}
+
+/// The request for one attempt, with secrets already resolved into `url` and `headers`
+fn build_request(
+    client: &reqwest::Client,
+    method: reqwest::Method,
+    job: &job::Model,
+    config: &WorkerConfig,
+    url: &str,
+    headers: Vec<(&str, String)>,
+) -> reqwest::RequestBuilder {
+    let mut request = client
+        .request(method, url)
+        .timeout(config.request_timeout(job));
+
+    let webhook = config.signing.for_job(job);
+
+    let mut has_content_type = false;
+    for (name, value) in headers {
+        // Signature headers come from the secret, never from the producer
+        if webhook.is_some()
+            && [
+                badger_webhooks::ID_HEADER,
+                badger_webhooks::TIMESTAMP_HEADER,
+                badger_webhooks::SIGNATURE_HEADER,
+            ]
+            .iter()
+            .any(|h| name.eq_ignore_ascii_case(h))
+        {
+            continue;
+        }
+        // An explicit content_type replaces the one in the headers
+        if name.eq_ignore_ascii_case("content-type") {
+            if job.content_type.is_some() {
+                continue;
+            }
+            has_content_type = true;
+        }
+        match (
+            reqwest::header::HeaderName::from_bytes(name.as_bytes()),
+            reqwest::header::HeaderValue::from_str(&value),
+        ) {
+            (Ok(name), Ok(value)) => request = request.header(name, value),
+            _ => tracing::error!(
+                "Job {} has an invalid header {:?}, skipping it",
+                job.id,
+                name
+            ),
+        }
+    }
+
+    let encoding = job.body_encoding.unwrap_or_default();
+    let payload = if job.body.is_null() {
+        None
+    } else {
+        match body::encode(&job.body, encoding) {
+            Ok(bytes) => Some(bytes),
+            Err(e) => {
+                tracing::error!("Job {} has a body that cannot be sent: {}", job.id, e);
+                None
+            }
+        }
+    };
+
+    if let Some(webhook) = webhook {
+        // A fresh timestamp per attempt, the id stays the same across retries
+        let msg_id = webhook_id(job);
+        let timestamp = Utc::now().timestamp();
+        let signature = webhook.sign(&msg_id, timestamp, payload.as_deref().unwrap_or_default());
+        request = request
+            .header(badger_webhooks::ID_HEADER, msg_id)
+            .header(badger_webhooks::TIMESTAMP_HEADER, timestamp)
+            .header(badger_webhooks::SIGNATURE_HEADER, signature);
+    }
+
+    if let Some(bytes) = payload {
+        if !has_content_type {
+            let content_type = job
+                .content_type
+                .as_deref()
+                .unwrap_or(body::default_content_type(encoding));
+            request = request.header(reqwest::header::CONTENT_TYPE, content_type);
+        }
+        request = request.body(bytes);
+    }
+
+    request
+}
+
+/// Sends the request and reads the response into an outcome
+async fn send_request(
+    request: reqwest::RequestBuilder,
+    job_id: Uuid,
+    body_limit: usize,
+) -> AttemptOutcome {
+    match request.send().await {
+        Ok(resp) => {
+            let status = resp.status();
+            let headers = headers_to_json(resp.headers());
+            let retry_after = match status {
+                reqwest::StatusCode::TOO_MANY_REQUESTS
+                | reqwest::StatusCode::SERVICE_UNAVAILABLE => resp
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| retry::parse_retry_after(v, Utc::now())),
+                _ => None,
+            };
+            let (captured, read_error) = response::capture(resp, body_limit).await;
+            // A body that could not be read to the end is kept as truncated
+            let failure_reason = match read_error {
+                Some(_) => Some(FailureReason::BodyRead),
+                None => (!status.is_success()).then_some(FailureReason::HttpStatus),
+            };
+            let error = read_error.map(|e| {
+                // The URL may contain resolved secrets
+                let e = e.without_url();
+                tracing::warn!(
+                    "Could not read the whole response for job {}: {}",
+                    job_id,
+                    e
+                );
+                error_chain(&e)
+            });
+            AttemptOutcome {
+                status: Some(status),
+                headers: Some(headers),
+                body: captured.text,
+                truncated: captured.truncated,
+                length: captured.length,
+                error,
+                failure_reason,
+                retry_after,
+            }
+        }
+        Err(e) => {
+            let e = e.without_url();
+            let reason = classify_send_error(&e);
+            tracing::error!("HTTP error for job {} ({}): {}", job_id, reason.as_str(), e);
+            AttemptOutcome {
+                status: None,
+                headers: None,
+                body: String::new(),
+                truncated: false,
+                length: None,
+                error: Some(error_chain(&e)),
+                failure_reason: Some(reason),
+                retry_after: None,
+            }
+        }
+    }
+}
+
+/// A job's URL and header pairs, ready to send
+type ResolvedRequest<'a> = (String, Vec<(&'a str, String)>);
+
+/// The job's URL and headers with every `{{secret:NAME}}` placeholder replaced,
+/// once the secret's hosts allow it
+fn resolve_secrets<'a>(
+    job: &'a job::Model,
+    provider: &dyn SecretProvider,
+    hosts: &SecretHosts,
+) -> Result<ResolvedRequest<'a>, String> {
+    let host = url_host(&job.url);
+    let resolve = |text: &str| {
+        hosts.check(text, host.as_deref())?;
+        secrets::resolve(text, provider)
+    };
+    let url = resolve(&job.url)?;
+    let headers = headers::pairs(&job.headers)
+        .into_iter()
+        .map(|(name, value)| Ok((name, resolve(&value)?)))
+        .collect::<Result<_, String>>()?;
+    Ok((url, headers))
+}
+
+/// A running job's place in the pool. Dropping it frees the place for the next job.
+struct WorkerSlot {
+    index: usize,
+    free: Arc<std::sync::Mutex<Vec<usize>>>,
+    _permit: tokio::sync::OwnedSemaphorePermit,
+}
+
+impl Drop for WorkerSlot {
+    fn drop(&mut self) {
+        self.free.lock().unwrap().push(self.index);
+    }
+}
+
+/// What a finished job writes back: its attempt, if a request was made, and its new state
+struct Completion {
+    job_id: Uuid,
+    attempt: Option<job_attempt::ActiveModel>,
+    update: job::ActiveModel,
+}
+
+impl Completion {
+    /// Returns a job cut off by shutdown to `Pending`, as if it had never been
+    /// claimed, so another worker runs it right away without using up an attempt
+    fn released(job_id: Uuid, attempts: i32) -> Self {
+        Completion {
+            job_id,
+            attempt: None,
+            update: job::ActiveModel {
+                status: Set(entity::sea_orm_active_enums::StatusEnum::Pending),
+                attempts: Set((attempts - 1).max(0)),
+                check_in: Set(None),
+                updated_at: Set(Utc::now().naive_utc()),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Claims up to `limit` due jobs of `queue` in one statement, moving them to
+/// `Running`. Jobs are taken by priority, then by how long they have been due.
+async fn claim_jobs(
+    db: &DatabaseConnection,
+    queue: &str,
+    limit: u64,
+    priority_aging: Option<std::time::Duration>,
+) -> Result<Vec<job::Model>, DbErr> {
+    use entity::sea_orm_active_enums::StatusEnum;
+
+    let now = Utc::now().naive_utc();
+
+    let mut due = job::Entity::find()
+        .select_only()
+        .column(job::Column::Id)
+        .filter(job::Column::Queue.eq(queue))
+        .filter(job::Column::Status.eq(StatusEnum::Pending))
+        .filter(
+            Condition::any()
+                .add(job::Column::NextRunAt.lte(now))
+                .add(job::Column::NextRunAt.is_null()),
+        )
+        .limit(limit)
+        .lock_with_behavior(LockType::Update, LockBehavior::SkipLocked);
+
+    due = match priority_aging {
+        // Each level moves a job's due time earlier by one aging interval, so a
+        // job that has waited long enough overtakes newer, higher priority ones.
+        // No index serves this expression: every due job of the queue is read and
+        // sorted on each claim, which is cheap only while the due backlog is small.
+        Some(aging) => {
+            let secs = aging.as_secs() as i64;
+            let aged_due = match db.get_database_backend() {
+                DbBackend::Postgres => Expr::cust_with_values(
+                    "next_run_at - make_interval(secs => priority * $1)",
+                    [secs],
+                ),
+                _ => Expr::cust_with_values(
+                    "julianday(next_run_at) - priority * ? / 86400.0",
+                    [secs],
+                ),
+            };
+            due.order_by(aged_due, Order::Asc)
+        }
+        None => due
+            .order_by_desc(job::Column::Priority)
+            .order_by_asc(job::Column::NextRunAt),
+    };
+    let due = due.order_by_asc(job::Column::CreatedAt).into_query();
+
+    // Postgres may rescan an `IN (subquery)` once per row and so lock more jobs
+    // than the limit. An array is built once.
+    let claimed = match db.get_database_backend() {
+        DbBackend::Postgres => Expr::cust_with_exprs(
+            "$1 = ANY(ARRAY($2))",
+            [
+                Expr::col(job::Column::Id).into(),
+                SimpleExpr::SubQuery(None, Box::new(due.into_sub_query_statement())),
+            ],
+        ),
+        _ => job::Column::Id.in_subquery(due),
+    };
+
+    job::Entity::update_many()
+        .set(job::ActiveModel {
+            status: Set(StatusEnum::Running),
+            updated_at: Set(now),
+            check_in: Set(Some(now)),
+            ..Default::default()
+        })
+        .col_expr(
+            job::Column::Attempts,
+            Expr::col(job::Column::Attempts).add(1),
+        )
+        .filter(claimed)
+        // Repeated for SQLite, which has no row locks to skip
+        .filter(job::Column::Status.eq(StatusEnum::Pending))
+        .exec_with_returning(db)
+        .await
+}
+
+/// Claims due jobs of one queue in batches and runs each one in a free worker
+/// slot, at most `worker_count` at a time. Every queue served by the process has
+/// its own dispatcher, so a flood on one queue cannot take the slots of another.
+/// Returns once a shutdown has started and every running job has been written back.
+async fn dispatcher_task(
+    state: AppState,
+    config: WorkerConfig,
+    clients: HttpClients,
+    queue: String,
+    worker_count: usize,
+    completions: tokio::sync::mpsc::Sender<Completion>,
+    shutdown: Shutdown,
+) {
+    let semaphore = Arc::new(tokio::sync::Semaphore::new(worker_count));
+    let free = Arc::new(std::sync::Mutex::new(
+        (0..worker_count).rev().collect::<Vec<_>>(),
+    ));
+
+    let pid = std::process::id();
+
+    while !shutdown.is_draining() {
+        let ready = wakeup::ready(&state.wakeup);
+
+        // Wait for one free slot, then take every other free one up to the batch size
+        let permit = tokio::select! {
+            permit = semaphore.clone().acquire_owned() => {
+                permit.expect("The semaphore is never closed")
+            }
+            _ = shutdown.draining() => break,
+        };
+        let mut permits = vec![permit];
+        while permits.len() < config.claim_batch_size {
+            match semaphore.clone().try_acquire_owned() {
+                Ok(permit) => permits.push(permit),
+                Err(_) => break,
+            }
+        }
+        let wanted = permits.len();
+
+        // A paused queue keeps its jobs Pending, the running ones still finish
+        let paused = match queues::is_paused(&state.db, &queue).await {
+            Ok(paused) => paused,
+            Err(e) => {
+                tracing::error!("Failed to read the state of queue {}: {}", queue, e);
+                true
+            }
+        };
+        metrics::gauge!("queue_paused", "queue" => queue.clone()).set(u8::from(paused));
+
+        let jobs = if paused {
+            Vec::new()
+        } else {
+            match claim_jobs(&state.db, &queue, wanted as u64, config.priority_aging).await {
+                Ok(jobs) => jobs,
+                Err(e) => {
+                    tracing::error!("Failed to claim jobs from queue {}: {}", queue, e);
+                    Vec::new()
+                }
+            }
+        };
+        let claimed = jobs.len();
+        metrics::histogram!("job_claim_batch_size", "queue" => queue.clone())
+            .record(claimed as f64);
+
+        // Slots left over are released when their permits drop
+        for (job, permit) in jobs.into_iter().zip(permits) {
+            let slot = WorkerSlot {
+                index: free
+                    .lock()
+                    .unwrap()
+                    .pop()
+                    .expect("A permit always has a slot"),
+                free: free.clone(),
+                _permit: permit,
+            };
+            let worker_id = format!("{}-{}-{}", pid, queue, slot.index);
+            let state = state.clone();
+            let config = config.clone();
+            let clients = clients.clone();
+            let completions = completions.clone();
+            let shutdown = shutdown.clone();
+            let busy = metrics::gauge!("queue_workers_busy", "queue" => queue.clone());
+            busy.increment(1.0);
+            tokio::spawn(async move {
+                let (job_id, attempts) = (job.id, job.attempts);
+                let completion = tokio::select! {
+                    completion = run_job(&state, &config, &clients, &worker_id, job) => completion,
+                    // Dropping the request aborts it. The target may still have
+                    // acted on it, and will see it again from the next worker.
+                    _ = shutdown.expired() => {
+                        tracing::warn!("Job {} did not finish before shutdown, releasing it", job_id);
+                        Completion::released(job_id, attempts)
+                    }
+                };
+                // The slot is only freed once the result is queued for writing
+                if completions.send(completion).await.is_err() {
+                    tracing::error!("The completion writer has stopped");
+                }
+                drop(slot);
+                busy.decrement(1.0);
+            });
+        }
+
+        // A full batch means more jobs may be due, otherwise wait for new ones
+        if claimed < wanted {
+            tracing::debug!("No more pending jobs in queue {}", queue);
+            // Woken by the listener on Postgres. The poll still finds scheduled jobs,
+            // retries coming due, and every job on SQLite.
+            // Jitter keeps several badger processes from polling in lockstep
+            let jitter = rand::rng().random_range(0..=1000);
+            let poll = std::time::Duration::from_millis(5000 + jitter);
+            tokio::select! {
+                _ = tokio::time::timeout(poll, ready) => {}
+                _ = shutdown.draining() => {}
+            }
+        }
+    }
+
+    // Every slot comes back once its job has been queued for writing, finished or released
+    let _ = semaphore.acquire_many(worker_count as u32).await;
+    info!("Queue {} drained", queue);
+}
+
+/// Writes finished jobs back, as many per transaction as have queued up
+async fn completion_task(db: DatabaseConnection, mut rx: tokio::sync::mpsc::Receiver<Completion>) {
+    let mut batch = Vec::with_capacity(COMPLETION_BATCH_SIZE);
+
+    while rx.recv_many(&mut batch, COMPLETION_BATCH_SIZE).await > 0 {
+        metrics::histogram!("job_completion_batch_size").record(batch.len() as f64);
+
+        if let Err(e) = write_completions(&db, &batch).await {
+            // One bad row must not lose the others
+            tracing::warn!(
+                "Failed to write {} completions together, retrying one by one: {}",
+                batch.len(),
+                e
+            );
+            for completion in &batch {
+                if let Err(e) = write_completions(&db, std::slice::from_ref(completion)).await {
+                    tracing::error!(
+                        "Failed to update job {} after execution: {}",
+                        completion.job_id,
+                        e
+                    );
+                }
+            }
+        }
+        batch.clear();
+    }
+}
+
+async fn write_completions(db: &DatabaseConnection, batch: &[Completion]) -> Result<(), DbErr> {
+    let txn = db.begin().await?;
+
+    // The attempt happened even if the job was cancelled meanwhile
+    let attempts: Vec<job_attempt::ActiveModel> =
+        batch.iter().filter_map(|c| c.attempt.clone()).collect();
+    if !attempts.is_empty() {
+        job_attempt::Entity::insert_many(attempts)
+            .exec(&txn)
+            .await?;
+    }
+
+    for completion in batch {
+        if !update_if_running(&txn, completion.job_id, completion.update.clone()).await? {
+            info!(
+                "Job {} was cancelled while running, keeping Cancelled status",
+                completion.job_id
+            );
+        }
+    }
+
+    txn.commit().await
+}
+
+/// Runs one claimed job and returns what to write back
+async fn run_job(
+    state: &AppState,
+    config: &WorkerConfig,
+    clients: &HttpClients,
+    worker_id: &str,
+    job: job::Model,
+) -> Completion {
+    let start = Instant::now();
+
+    // `updated_at` is when the job was claimed
+    let delta = job.updated_at - job.next_run_at;
+    let lag = delta.to_std().map(|d| d.as_secs_f64()).unwrap_or(0.0);
+
+    metrics::histogram!("job_queue_lag_seconds", "queue" => job.queue.clone()).record(lag);
+
+    let completion = execute_job(state, config, clients, worker_id, &job)
+        .instrument(info_span!("Processing job", job_id = %job.id, queue = %job.queue))
+        .await;
+
+    let duration = start.elapsed().as_secs_f64();
+
+    metrics::histogram!("job_execution_duration_seconds", "queue" => job.queue.clone())
+        .record(duration);
+
+    completion
+}
+
+async fn execute_job(
+    state: &AppState,
+    config: &WorkerConfig,
+    clients: &HttpClients,
+    worker_id: &str,
+    job: &job::Model,
+) -> Completion {
+    info!("Job picked up");
+
+    let done = |attempt, update| Completion {
+        job_id: job.id,
+        attempt,
+        update,
+    };
+
+    let method = match reqwest::Method::from_bytes(job.method.as_bytes()) {
+        Ok(m) => m,
+        Err(_) => {
+            tracing::error!("Invalid HTTP method for job {}", job.id);
+            // Mark as Failure — otherwise the job is stuck in Running forever
+            let mut active = job.clone().into_active_model();
+            active.status = Set(entity::sea_orm_active_enums::StatusEnum::Failure);
+            active.updated_at = Set(Utc::now().naive_utc());
+            return done(None, active);
+        }
+    };
+
+    let url = match Url::parse(&job.url) {
+        Ok(u) => u,
+        Err(e) => {
+            tracing::error!("Failed to parse URL for job {}: {}", job.id, e);
+            // Mark as Failure — otherwise the job is stuck in Running forever
+            let mut active = job.clone().into_active_model();
+            active.status = Set(entity::sea_orm_active_enums::StatusEnum::Failure);
+            active.updated_at = Set(Utc::now().naive_utc());
+            return done(None, active);
+        }
+    };
+
+    let mut active = job.clone().into_active_model();
+
+    match url.host_str() {
+        Some(host) => {
+            if let Err(nbd) = state.limiter.check_key(&host.to_string()) {
+                let now = state.limiter.clock().now();
+                let wait_dur = nbd.wait_time_from(now);
+                let next_available_utc = Utc::now() + chrono::Duration::from_std(wait_dur).unwrap();
+
+                // Roll back the attempt/retry increment from pick-up
+                let attempts = (job.attempts - 1).max(0);
+                active.status = Set(entity::sea_orm_active_enums::StatusEnum::Pending);
+                active.updated_at = Set(Utc::now().naive_utc());
+                active.next_run_at = Set(next_available_utc.naive_utc());
+                active.attempts = Set(attempts);
+                active.retries = Set((attempts - 1).max(0));
+
+                tracing::warn!(
+                    "Rate limited for host {}, next available at {}",
+                    host,
+                    next_available_utc
+                );
+                return done(None, active);
+            }
+        }
+        None => {
+            tracing::error!("Job {} has no valid host in URL {}", job.id, url);
+            active.status = Set(entity::sea_orm_active_enums::StatusEnum::Failure);
+            active.updated_at = Set(Utc::now().naive_utc());
+            // Roll back attempt increment — this was a bad job, not a real attempt
+            let attempts = (job.attempts - 1).max(0);
+            active.attempts = Set(attempts);
+            active.retries = Set((attempts - 1).max(0));
+            return done(None, active);
+        }
+    }
+
+    let started_at = Utc::now();
+
+    // Only the request sees the decrypted payload, the row is updated from `job`.
+    // The row was validated on submit but may have been changed in the database
+    // since, so it is checked again on the way out.
+    let outcome = match state.keyring.decrypt(job.clone()) {
+        Ok(plain) => {
+            // Resolved on every attempt, so a rotated secret applies to queued jobs too
+            match resolve_secrets(&plain, config.secrets.as_ref(), &state.secret_hosts) {
+                Ok((url, headers)) => match clients.get(WorkerConfig::connect_timeout(job)) {
+                    Ok(client) => {
+                        let request = build_request(&client, method, &plain, config, &url, headers);
+                        send_request(request, job.id, config.response_body_limit).await
+                    }
+                    Err(e) => {
+                        tracing::error!("Cannot build an HTTP client for job {}: {}", job.id, e);
+                        AttemptOutcome::not_sent(error_chain(&e), FailureReason::Connect)
+                    }
+                },
+                Err(e) => {
+                    tracing::error!("Cannot resolve secrets for job {}: {}", job.id, e);
+                    AttemptOutcome::not_sent(e, FailureReason::Secret)
+                }
+            }
+        }
+        Err(e) => {
+            tracing::error!("Cannot decrypt job {}: {}", job.id, e);
+            AttemptOutcome::not_sent(e, FailureReason::Decryption)
+        }
+    };
+
+    let attempt = attempt_model(job, worker_id, started_at, Utc::now(), &outcome);
+    let retry = config.retry.for_job(job);
+
+    // The response has its own columns, the request body is never touched
+    active.response_status = Set(outcome.status.map(|s| s.as_u16() as i32));
+    active.response_headers = Set(outcome.headers);
+    active.response_truncated = Set(outcome.status.map(|_| outcome.truncated));
+    active.response_length = Set(outcome.length);
+    active.response_body = Set(outcome.status.map(|_| outcome.body));
+    active.failure_reason = Set(outcome.failure_reason);
+
+    // Once the target has answered, its status alone decides the outcome, so a
+    // 2xx whose body could not be read still succeeds
+    let failure = outcome
+        .failure_reason
+        .filter(|_| !outcome.status.is_some_and(|status| status.is_success()));
+    match failure {
+        None => {
+            let cron_exp = job.cron.clone();
+
+            match cron_exp {
+                Some(exp) => {
+                    let next_time = next_execution_time(exp.clone());
+
+                    if let Some(dt) = next_time {
+                        active.status = Set(entity::sea_orm_active_enums::StatusEnum::Pending);
+                        active.next_run_at = Set(dt.naive_utc());
+                        active.scheduled_at = Set(Some(dt.naive_utc()));
+                        active.attempts = Set(0);
+                        active.retries = Set(0);
+                    } else {
+                        active.status = Set(entity::sea_orm_active_enums::StatusEnum::Failure);
+                        tracing::error!("Cron expression for job {} is invalid: {}", job.id, exp);
+                    }
+                }
+                None => {
+                    active.status = Set(entity::sea_orm_active_enums::StatusEnum::Success);
+                }
+            }
+
+            active.updated_at = Set(Utc::now().naive_utc());
+            active.retries = Set((job.attempts - 1).max(0));
+
+            metrics::counter!(
+                "job_execution_result",
+                "status" => "success",
+                "queue" => job.queue.clone()
+            )
+            .increment(1);
+        }
+        Some(reason) => {
+            let attempts = job.attempts;
+            active.retries = Set((attempts - 1).max(0));
+
+            let retryable = retry.should_retry(reason, outcome.status);
+
+            if !retryable || attempts >= retry.max_attempts {
+                active.status = Set(entity::sea_orm_active_enums::StatusEnum::Failure);
+            } else {
+                active.status = Set(entity::sea_orm_active_enums::StatusEnum::Pending);
+
+                // The target's Retry-After wins over our own backoff
+                let delay = outcome
+                    .retry_after
+                    .map(|requested| retry.retry_after(requested))
+                    .unwrap_or_else(|| retry.delay(attempts));
+                let next_time = (Utc::now() + delay).naive_utc();
+                active.next_run_at = Set(next_time);
+            }
+
+            active.updated_at = Set(Utc::now().naive_utc());
+
+            metrics::counter!(
+                "job_execution_result",
+                "status" => "failure",
+                "reason" => reason.as_str(),
+                "queue" => job.queue.clone()
+            )
+            .increment(1);
+        }
+    }
+
+    done(Some(attempt), active)
+}
+
+/// Running jobs that have not finished within their request timeout plus
+/// `STALE_GRACE` since they were claimed, so their worker is presumed dead. Jobs
+/// without `timeout_ms` use `default_timeout`.
+fn stale_condition(
+    backend: DbBackend,
+    now: NaiveDateTime,
+    default_timeout: std::time::Duration,
+) -> Condition {
+    let values = [
+        sea_orm::Value::from(default_timeout.as_millis() as i64),
+        sea_orm::Value::from(STALE_GRACE.num_milliseconds()),
+        sea_orm::Value::from(now),
+    ];
+    // Compared in SQL, so healthy long-running jobs cannot crowd out stale ones
+    let overdue = match backend {
+        DbBackend::Postgres => Expr::cust_with_values(
+            "COALESCE(check_in, updated_at) \
+             + make_interval(secs => (COALESCE(timeout_ms, $1) + $2) / 1000.0) <= $3",
+            values,
+        ),
+        _ => Expr::cust_with_values(
+            "julianday(COALESCE(check_in, updated_at)) \
+             + (COALESCE(timeout_ms, ?) + ?) / 86400000.0 <= julianday(?)",
+            values,
+        ),
+    };
+
+    Condition::all()
+        .add(job::Column::Status.eq(entity::sea_orm_active_enums::StatusEnum::Running))
+        .add(overdue)
+}
+
+async fn monitor_task(state: AppState, config: WorkerConfig) {
+    // Queues whose depth has been reported, so an emptied one drops back to 0
+    let mut seen_queues: std::collections::HashSet<String> = std::collections::HashSet::new();
+
+    loop {
+        let now = Utc::now().naive_utc();
+        let backend = state.db.get_database_backend();
+
+        let jobs = job::Entity::find()
+            .filter(stale_condition(backend, now, config.request_timeout))
+            .order_by_asc(job::Column::UpdatedAt)
+            .limit(100)
+            .all(&state.db)
+            .await;
+
+        match jobs {
+            Ok(jobs) => {
+                let mut recovered = 0;
+
+                for job in jobs {
+                    let job_id = job.id;
+                    let mut active_job = job.into_active_model();
+                    active_job.check_in = Set(Some(Utc::now().naive_utc()));
+                    active_job.status = Set(entity::sea_orm_active_enums::StatusEnum::Pending);
+
+                    match update_if_running(&state.db, job_id, active_job).await {
+                        Ok(true) => recovered += 1,
+                        Ok(false) => {}
+                        Err(e) => tracing::error!("Error while processing job: {}", e),
+                    }
+                }
+
+                if recovered == 0 {
+                    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                }
+            }
+            Err(e) => {
+                tracing::error!("Error fetching job: {}", e);
+                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+            }
+        }
 
         // Measure Queue Depth
         let now = Utc::now().naive_utc();
         let pending_jobs = job::Entity::find()
+            .select_only()
+            .column(job::Column::Queue)
+            .column_as(job::Column::Id.count(), "count")
             .filter(job::Column::Status.eq(entity::sea_orm_active_enums::StatusEnum::Pending))
             .filter(
                 job::Column::NextRunAt
                     .is_null()
                     .or(job::Column::NextRunAt.lt(now)),
             )
-            .count(&state.db)
+            .group_by(job::Column::Queue)
+            .into_tuple::<(String, i64)>()
+            .all(&state.db)
             .await;
 
-        if let Ok(count) = pending_jobs {
-            metrics::gauge!("job_queue_depth").set(count as f64);
-        } else if let Err(e) = pending_jobs {
-            eprintln!("Error fetching pending jobs count: {}", e);
+        match pending_jobs {
+            Ok(counts) => {
+                for queue in &seen_queues {
+                    if !counts.iter().any(|(name, _)| name == queue) {
+                        metrics::gauge!("job_queue_depth", "queue" => queue.clone()).set(0.0);
+                    }
+                }
+                for (queue, count) in counts {
+                    metrics::gauge!("job_queue_depth", "queue" => queue.clone()).set(count as f64);
+                    seen_queues.insert(queue);
+                }
+            }
+            Err(e) => tracing::error!("Error fetching pending jobs count: {}", e),
+        }
+    }
+}
+
+async fn retention_task(state: AppState, config: RetentionConfig) {
+    loop {
+        for (status, ttl) in &config.ttls {
+            let cutoff = Utc::now().naive_utc() - *ttl;
+            let condition = Condition::all()
+                .add(job::Column::Status.eq(status.clone()))
+                .add(job::Column::UpdatedAt.lt(cutoff));
+
+            match delete_in_batches(&state.db, condition, config.batch_size).await {
+                Ok(0) => {}
+                Ok(purged) => {
+                    info!("Retention deleted {} {:?} jobs", purged, status);
+                    metrics::counter!("job_purged_total", "status" => format!("{:?}", status))
+                        .increment(purged);
+                }
+                Err(e) => tracing::error!("Error deleting expired {:?} jobs: {}", status, e),
+            }
         }
+
+        tokio::time::sleep(config.interval).await;
     }
 }
 
 #[tokio::main]
 async fn main() {
+    use entity::sea_orm_active_enums::StatusEnum;
+
     tracing_subscriber::fmt::init();
     dotenv().ok();
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("secret") {
+        if let Err(e) = secrets::run_command(&args[1..]) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if args.first().map(String::as_str) == Some("encryption") {
+        let db_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+        let db = sea_orm::Database::connect(db_url).await.unwrap();
+        if let Err(e) = encryption::run_command(&args[1..], &db).await {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // Load configuration from environment variables
-    let port = std::env::var("BADGER_PORT")
-        .unwrap_or_else(|_| "3000".to_string());
+    let port = std::env::var("BADGER_PORT").unwrap_or_else(|_| "3000".to_string());
     let worker_count: usize = std::env::var("WORKER_COUNT")
         .unwrap_or_else(|_| "10".to_string())
         .parse()
         .unwrap_or(10);
+    // Queues served by this process and the jobs each may run at once
+    let served_queues = queues::parse(&std::env::var("QUEUES").unwrap_or_default(), worker_count)
+        .expect("QUEUES is not valid");
+    // How long running jobs may take to finish once a shutdown starts
+    let shutdown_grace = std::time::Duration::from_secs(
+        std::env::var("SHUTDOWN_GRACE_SECS")
+            .unwrap_or_else(|_| "20".to_string())
+            .parse()
+            .unwrap_or(20),
+    );
     let max_retries: i32 = std::env::var("MAX_RETRIES")
         .unwrap_or_else(|_| "10".to_string())
         .parse()
         .unwrap_or(10);
 
+    let worker_config = WorkerConfig {
+        retry: RetryConfig {
+            max_attempts: max_retries,
+            strategy: std::env::var("RETRY_STRATEGY")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(BackoffStrategy::Exponential),
+            base_delay_ms: std::env::var("RETRY_BASE_DELAY_MS")
+                .unwrap_or_else(|_| "1000".to_string())
+                .parse()
+                .unwrap_or(1000),
+            max_delay_ms: std::env::var("RETRY_MAX_DELAY_MS")
+                .unwrap_or_else(|_| "3600000".to_string())
+                .parse::<u64>()
+                .unwrap_or(3_600_000)
+                .min(retry::MAX_DELAY_MS),
+            jitter: std::env::var("RETRY_JITTER")
+                .unwrap_or_else(|_| "0.1".to_string())
+                .parse::<f64>()
+                .unwrap_or(0.1)
+                .clamp(0.0, 1.0),
+            retryable_statuses: retry::parse_statuses(
+                &std::env::var("RETRYABLE_STATUSES").unwrap_or_else(|_| "408,425,429".to_string()),
+            ),
+            non_retryable_statuses: retry::parse_statuses(
+                &std::env::var("NON_RETRYABLE_STATUSES").unwrap_or_default(),
+            ),
+            non_retryable_reasons: retry::parse_reasons(
+                &std::env::var("NON_RETRYABLE_REASONS").unwrap_or_default(),
+            ),
+        },
+        request_timeout: std::time::Duration::from_millis(
+            std::env::var("REQUEST_TIMEOUT_MS")
+                .unwrap_or_else(|_| "30000".to_string())
+                .parse()
+                .unwrap_or(30000),
+        ),
+        connect_timeout: std::time::Duration::from_millis(
+            std::env::var("CONNECT_TIMEOUT_MS")
+                .unwrap_or_else(|_| "10000".to_string())
+                .parse()
+                .unwrap_or(10000),
+        ),
+        response_body_limit: std::env::var("RESPONSE_BODY_LIMIT_BYTES")
+            .unwrap_or_else(|_| "1048576".to_string())
+            .parse()
+            .unwrap_or(1_048_576),
+        claim_batch_size: std::env::var("CLAIM_BATCH_SIZE")
+            .unwrap_or_else(|_| "100".to_string())
+            .parse::<usize>()
+            .unwrap_or(100)
+            .max(1),
+        priority_aging: std::env::var("PRIORITY_AGING_SECS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|&secs| secs > 0)
+            .map(std::time::Duration::from_secs),
+        signing: SigningConfig {
+            global: std::env::var("WEBHOOK_SECRET").ok().map(|secret| {
+                badger_webhooks::Webhook::new(&secret)
+                    .expect("WEBHOOK_SECRET is not a valid secret")
+            }),
+            by_host: signing::parse_host_secrets(
+                &std::env::var("WEBHOOK_HOST_SECRETS").unwrap_or_default(),
+            )
+            .expect("WEBHOOK_HOST_SECRETS is not valid"),
+        },
+        secrets: secrets::provider_from_env().expect("Invalid secret provider configuration"),
+    };
+    let keyring = Keyring::from_env().expect("Invalid ENCRYPTION_KEYS");
+    let secret_hosts = SecretHosts::from_env().expect("Invalid SECRET_HOSTS");
+
+    // Retention TTLs in seconds, per terminal status
+    let retention = RetentionConfig {
+        ttls: [
+            (StatusEnum::Success, "RETENTION_SUCCESS_SECS"),
+            (StatusEnum::Failure, "RETENTION_FAILURE_SECS"),
+            (StatusEnum::Cancelled, "RETENTION_CANCELLED_SECS"),
+        ]
+        .into_iter()
+        .filter_map(|(status, var)| {
+            let secs: i64 = std::env::var(var).ok()?.parse().ok()?;
+            Some((status, Duration::seconds(secs)))
+        })
+        .collect(),
+        batch_size: std::env::var("RETENTION_BATCH_SIZE")
+            .unwrap_or_else(|_| "500".to_string())
+            .parse()
+            .unwrap_or(500)
+            .max(1),
+        interval: std::time::Duration::from_secs(
+            std::env::var("RETENTION_INTERVAL_SECS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+        ),
+    };
+
     let recorder_handle = PrometheusBuilder::new()
         .install_recorder()
         .expect("failed to install recorder");
@@ -556,8 +2470,30 @@ async fn main() {
     println!("Database connection established");
     println!("Configuration:");
     println!("  - Port: {}", port);
-    println!("  - Workers: {}", worker_count);
+    for (queue, concurrency) in &served_queues {
+        println!("  - Queue {}: {} workers", queue, concurrency);
+    }
+    println!("  - Shutdown Grace: {}s", shutdown_grace.as_secs());
     println!("  - Max Retries: {}", max_retries);
+    println!(
+        "  - Retry Backoff: {:?}, base {}ms, max {}ms, jitter {}",
+        worker_config.retry.strategy,
+        worker_config.retry.base_delay_ms,
+        worker_config.retry.max_delay_ms,
+        worker_config.retry.jitter
+    );
+    println!(
+        "  - Request Timeout: {}ms (connect {}ms)",
+        worker_config.request_timeout.as_millis(),
+        worker_config.connect_timeout.as_millis()
+    );
+    println!(
+        "  - Payload Encryption: {}",
+        keyring.active_key_id().unwrap_or("off")
+    );
+    for (status, ttl) in &retention.ttls {
+        println!("  - Retention ({:?}): {}s", status, ttl.num_seconds());
+    }
 
     let quota = Quota::per_second(NonZeroU32::new(5).unwrap());
     let limiter = Arc::new(RateLimiter::new(
@@ -567,27 +2503,79 @@ async fn main() {
     ));
 
     // Axum router setup
-    let state = AppState { db, limiter };
-
-    // Spawn workers
-    let mut worker_handles = Vec::new();
-    for i in 0..worker_count {
-        let worker_state = state.clone();
-        let worker = tokio::spawn(async move {
-            println!("Worker {} started", i);
-            worker_task(worker_state, max_retries).await;
-        });
-        worker_handles.push(worker);
-    }
+    let state = AppState {
+        db,
+        limiter,
+        keyring: Arc::new(keyring),
+        secret_hosts: Arc::new(secret_hosts),
+        wakeup: Arc::new(tokio::sync::Notify::new()),
+    };
+
+    // Wakes idle workers as soon as a job is ready, Postgres only
+    tokio::spawn(wakeup::listen_task(state.db.clone(), state.wakeup.clone()));
+
+    // Built once and shared by every job. The total timeout is set per request.
+    let clients =
+        HttpClients::new(worker_config.connect_timeout).expect("Failed to build HTTP client");
+
+    // SIGINT or SIGTERM stops new claims and, after the grace period, releases
+    // the jobs still running
+    let shutdown = Shutdown::listen(shutdown_grace);
+
+    // Bounded, so slow writes hold back new claims instead of piling up. With
+    // WORKER_COUNT=0 the process only serves the API and nothing is ever sent.
+    let total_workers = served_queues.iter().map(|(_, n)| n).sum::<usize>();
+    let (completions, rx) = tokio::sync::mpsc::channel(total_workers.max(1));
+    let writer = tokio::spawn(completion_task(state.db.clone(), rx));
+
+    // One dispatcher per queue claims its jobs and runs up to its concurrency at once
+    let dispatchers: Vec<_> = served_queues
+        .into_iter()
+        .filter(|(_, concurrency)| *concurrency > 0)
+        .map(|(queue, concurrency)| {
+            tokio::spawn(dispatcher_task(
+                state.clone(),
+                worker_config.clone(),
+                clients.clone(),
+                queue,
+                concurrency,
+                completions.clone(),
+                shutdown.clone(),
+            ))
+        })
+        .collect();
+    // The writer stops once every dispatcher and job has dropped its sender
+    drop(completions);
 
     let monitor_state = state.clone();
-    let monitor = tokio::spawn(async move {
-        monitor_task(monitor_state).await;
+    tokio::spawn(async move {
+        monitor_task(monitor_state, worker_config).await;
     });
 
+    if !retention.ttls.is_empty() {
+        let retention_state = state.clone();
+        tokio::spawn(async move {
+            retention_task(retention_state, retention).await;
+        });
+    }
+
     let app = Router::new()
-        .route("/jobs", post(create_job))
-        .route("/jobs/{id}", axum::routing::get(get_job))
+        .route("/jobs", post(create_job).get(list_jobs))
+        .route(
+            "/jobs/{id}",
+            axum::routing::get(get_job)
+                .patch(update_job)
+                .delete(delete_job),
+        )
+        .route("/jobs/batch", post(create_jobs_batch))
+        .route("/jobs/retry", post(bulk_retry_jobs))
+        .route("/jobs/purge", post(purge_jobs))
+        .route("/jobs/{id}/cancel", post(cancel_job))
+        .route("/jobs/{id}/retry", post(retry_job))
+        .route("/jobs/{id}/attempts", get(list_job_attempts))
+        .route("/queues", get(list_queues))
+        .route("/queues/{name}/pause", post(pause_queue))
+        .route("/queues/{name}/resume", post(resume_queue))
         .route(
             "/metrics",
             get(move || std::future::ready(recorder_handle.render())),
@@ -597,5 +2585,1119 @@ async fn main() {
     let bind_addr = format!("0.0.0.0:{}", port);
     let listener = tokio::net::TcpListener::bind(&bind_addr).await.unwrap();
     println!("Server listening on port {}!", port);
-    axum::serve(listener, app).await.unwrap();
+    // Stops accepting connections on shutdown and finishes the requests in progress
+    let server_shutdown = shutdown.clone();
+    axum::serve(listener, app)
+        .with_graceful_shutdown(async move { server_shutdown.draining().await })
+        .await
+        .unwrap();
+
+    for dispatcher in dispatchers {
+        if let Err(e) = dispatcher.await {
+            tracing::error!("Dispatcher failed: {}", e);
+        }
+    }
+    // Everything finished or released is written before the process exits
+    if let Err(e) = writer.await {
+        tracing::error!("Completion writer failed: {}", e);
+    }
+    info!("Shutdown complete");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use entity::sea_orm_active_enums::StatusEnum;
+    use sea_orm::{Database, Schema};
+
+    /// An in-memory database with the schema the entities describe
+    async fn test_db() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let schema = Schema::new(db.get_database_backend());
+        let backend = db.get_database_backend();
+        for statement in [
+            schema.create_table_from_entity(job::Entity),
+            schema.create_table_from_entity(job_attempt::Entity),
+            schema.create_table_from_entity(queue::Entity),
+        ] {
+            db.execute(backend.build(&statement)).await.unwrap();
+        }
+        db
+    }
+
+    fn test_state(db: DatabaseConnection) -> AppState {
+        AppState {
+            db,
+            limiter: Arc::new(RateLimiter::keyed(Quota::per_second(
+                NonZeroU32::new(5).unwrap(),
+            ))),
+            keyring: Arc::new(Keyring::default()),
+            secret_hosts: Arc::new(SecretHosts::default()),
+            wakeup: Arc::new(tokio::sync::Notify::new()),
+        }
+    }
+
+    /// A keyring holding one new random key
+    fn random_keyring(id: &str) -> Keyring {
+        use base64::Engine;
+        let key: [u8; 32] = rand::random();
+        let encoded = base64::engine::general_purpose::STANDARD.encode(key);
+        Keyring::parse(&format!("{}={}", id, encoded), None).unwrap()
+    }
+
+    fn job_request(value: JsonValue) -> JobRequest {
+        serde_json::from_value(value).unwrap()
+    }
+
+    /// Inserts a job for `url` with `status` and returns it
+    async fn insert_job(db: &DatabaseConnection, url: &str, status: StatusEnum) -> job::Model {
+        let now = Utc::now().naive_utc();
+        let request = job_request(serde_json::json!({"url": url, "method": "GET"}));
+        let mut model = new_job_model(request, now, &Keyring::default());
+        model.status = Set(status);
+        model.insert(db).await.unwrap()
+    }
+
+    async fn insert_attempt(db: &DatabaseConnection, job: &job::Model) {
+        let now = Utc::now();
+        let outcome =
+            AttemptOutcome::not_sent("connection refused".to_string(), FailureReason::Connect);
+        attempt_model(job, "worker", now, now, &outcome)
+            .insert(db)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_webhook_id_is_kept_by_retries_and_changes_per_run() {
+        let db = test_db().await;
+        let mut job = insert_job(&db, "http://a.test/", StatusEnum::Pending).await;
+        let first = webhook_id(&job);
+        assert!(first.starts_with(&job.id.to_string()));
+
+        // A retry only moves next_run_at
+        job.next_run_at += Duration::seconds(30);
+        assert_eq!(webhook_id(&job), first);
+
+        // The next cron run
+        job.scheduled_at = Some(job.next_run_at + Duration::minutes(5));
+        assert_ne!(webhook_id(&job), first);
+    }
+
+    #[tokio::test]
+    async fn test_stale_condition_respects_job_timeout() {
+        let db = test_db().await;
+        let now = Utc::now().naive_utc();
+
+        // Claimed this long ago, with this timeout, and whether the worker is presumed dead
+        let cases = [
+            (Duration::seconds(20), None, false),
+            (Duration::seconds(50), None, true),
+            (Duration::seconds(50), Some(120_000), false),
+            (Duration::seconds(50), Some(2_000), true),
+        ];
+        let mut expected = Vec::new();
+        for (i, (age, timeout_ms, stale)) in cases.into_iter().enumerate() {
+            let job = insert_job(&db, &format!("http://{}.test/", i), StatusEnum::Running).await;
+            let mut active = job.clone().into_active_model();
+            active.check_in = Set(Some(now - age));
+            active.timeout_ms = Set(timeout_ms);
+            active.update(&db).await.unwrap();
+            if stale {
+                expected.push(job.id);
+            }
+        }
+        // Only Running jobs are ever stale
+        let pending = insert_job(&db, "http://pending.test/", StatusEnum::Pending).await;
+        let mut active = pending.into_active_model();
+        active.check_in = Set(Some(now - Duration::hours(1)));
+        active.update(&db).await.unwrap();
+
+        let condition = stale_condition(
+            db.get_database_backend(),
+            now,
+            std::time::Duration::from_secs(30),
+        );
+        let mut stale: Vec<Uuid> = job::Entity::find()
+            .filter(condition)
+            .all(&db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|job| job.id)
+            .collect();
+        stale.sort();
+        expected.sort();
+        assert_eq!(stale, expected);
+    }
+
+    /// Answers one connection with `response`, whatever the request, then closes it
+    async fn serve_once(response: &'static [u8]) -> std::net::SocketAddr {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).await;
+            let _ = stream.write_all(response).await;
+        });
+        addr
+    }
+
+    async fn send(url: &str) -> AttemptOutcome {
+        let clients = HttpClients::new(std::time::Duration::from_secs(5)).unwrap();
+        let request = clients.get(None).unwrap().get(url);
+        send_request(request, Uuid::new_v4(), 1024).await
+    }
+
+    #[tokio::test]
+    async fn test_send_errors_are_classified_by_their_source() {
+        let outcome = send("http://badger-test.invalid/").await;
+        assert_eq!(outcome.failure_reason, Some(FailureReason::Dns));
+
+        // Nothing listens on a port that was just freed
+        let addr = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let outcome = send(&format!("http://{}/", addr)).await;
+        assert_eq!(outcome.failure_reason, Some(FailureReason::Connect));
+
+        // Plain HTTP where a TLS handshake is expected
+        let addr = serve_once(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n").await;
+        let outcome = send(&format!("https://{}/", addr)).await;
+        assert_eq!(outcome.failure_reason, Some(FailureReason::Tls));
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_body_does_not_fail_a_success() {
+        // The connection closes 10 bytes into a 100 byte body
+        let addr = serve_once(b"HTTP/1.1 200 OK\r\ncontent-length: 100\r\n\r\n0123456789").await;
+        let outcome = send(&format!("http://{}/", addr)).await;
+        assert_eq!(outcome.status, Some(reqwest::StatusCode::OK));
+        assert_eq!(outcome.failure_reason, Some(FailureReason::BodyRead));
+        assert_eq!(outcome.body, "0123456789");
+        assert!(outcome.truncated);
+        assert!(outcome.error.is_some());
+
+        let addr =
+            serve_once(b"HTTP/1.1 500 Internal Server Error\r\ncontent-length: 100\r\n\r\nerr")
+                .await;
+        let outcome = send(&format!("http://{}/", addr)).await;
+        assert_eq!(outcome.failure_reason, Some(FailureReason::BodyRead));
+        assert_eq!(outcome.body, "err");
+
+        // The job still succeeds, and keeps the reason
+        let state = test_state(test_db().await);
+        let addr = serve_once(b"HTTP/1.1 200 OK\r\ncontent-length: 100\r\n\r\n0123456789").await;
+        insert_job(&state.db, &format!("http://{}/", addr), StatusEnum::Pending).await;
+        let [job] = &claim_jobs(&state.db, queues::DEFAULT, 1, None)
+            .await
+            .unwrap()[..]
+        else {
+            panic!("The job should be claimed");
+        };
+        let clients = HttpClients::new(std::time::Duration::from_secs(5)).unwrap();
+        let completion = execute_job(&state, &test_config(), &clients, "worker", job).await;
+        write_completions(&state.db, &[completion]).await.unwrap();
+
+        let stored = find_job(&state.db, job.id).await.unwrap();
+        assert_eq!(stored.status, StatusEnum::Success);
+        assert_eq!(stored.failure_reason, Some(FailureReason::BodyRead));
+        assert_eq!(stored.response_truncated, Some(true));
+    }
+
+    fn status_of(error: ApiError) -> axum::http::StatusCode {
+        axum::response::IntoResponse::into_response(error).status()
+    }
+
+    #[tokio::test]
+    async fn test_purge_needs_a_filter_or_all() {
+        let state = test_state(test_db().await);
+        insert_job(&state.db, "http://a.test/", StatusEnum::Success).await;
+        insert_job(&state.db, "http://b.test/", StatusEnum::Failure).await;
+        insert_job(&state.db, "http://c.test/", StatusEnum::Pending).await;
+        let purge = |body| {
+            let request: PurgeRequest = serde_json::from_value(body).unwrap();
+            purge_jobs(State(state.clone()), ApiJson(request))
+        };
+
+        let error = purge(serde_json::json!({})).await.err().unwrap();
+        assert_eq!(
+            status_of(error),
+            axum::http::StatusCode::UNPROCESSABLE_ENTITY
+        );
+        assert_eq!(job::Entity::find().count(&state.db).await.unwrap(), 3);
+
+        let purged = purge(serde_json::json!({"host": "a.test"})).await.unwrap();
+        assert_eq!(purged.purged, 1);
+
+        let purged = purge(serde_json::json!({"all": true})).await.unwrap();
+        assert_eq!(purged.purged, 1, "Pending jobs are never purged");
+    }
+
+    #[tokio::test]
+    async fn test_cancel_only_unfinished_jobs() {
+        let state = test_state(test_db().await);
+        let pending = insert_job(&state.db, "http://a.test/", StatusEnum::Pending).await;
+        let running = insert_job(&state.db, "http://b.test/", StatusEnum::Running).await;
+        let finished = insert_job(&state.db, "http://c.test/", StatusEnum::Success).await;
+        let cancel = |id| cancel_job(State(state.clone()), ApiPath(id));
+
+        for job in [&pending, &running] {
+            let cancelled = cancel(job.id).await.unwrap();
+            assert_eq!(cancelled.status, StatusEnum::Cancelled);
+        }
+        let again = cancel(pending.id).await.unwrap();
+        assert_eq!(
+            again.status,
+            StatusEnum::Cancelled,
+            "Cancelling twice is fine"
+        );
+
+        let error = cancel(finished.id).await.err().unwrap();
+        assert_eq!(status_of(error), axum::http::StatusCode::CONFLICT);
+        let finished = find_job(&state.db, finished.id).await.unwrap();
+        assert_eq!(finished.status, StatusEnum::Success);
+
+        let error = cancel(Uuid::new_v4()).await.err().unwrap();
+        assert_eq!(status_of(error), axum::http::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_retention_deletes_expired_jobs_in_batches() {
+        let state = test_state(test_db().await);
+        let db = state.db.clone();
+        let expired = (Utc::now() - Duration::days(8)).naive_utc();
+        let mut kept = Vec::new();
+        for (i, (status, old)) in [
+            (StatusEnum::Success, true),
+            (StatusEnum::Success, true),
+            (StatusEnum::Success, true),
+            (StatusEnum::Success, true),
+            (StatusEnum::Success, true),
+            (StatusEnum::Success, false),
+            (StatusEnum::Failure, true),
+            (StatusEnum::Failure, true),
+            (StatusEnum::Failure, true),
+            (StatusEnum::Pending, true),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let job = insert_job(&db, &format!("http://a.test/{}", i), status.clone()).await;
+            insert_attempt(&db, &job).await;
+            let mut active = job.clone().into_active_model();
+            if old {
+                active.updated_at = Set(expired);
+            }
+            let job = active.update(&db).await.unwrap();
+            if status != StatusEnum::Success || !old {
+                kept.push(job.id);
+            }
+        }
+        let remaining_jobs = || job::Entity::find().count(&db);
+        let remaining_attempts = || job_attempt::Entity::find().count(&db);
+
+        // More expired jobs than one batch holds, with their attempts
+        let condition = Condition::all()
+            .add(job::Column::Status.eq(StatusEnum::Success))
+            .add(job::Column::UpdatedAt.lt((Utc::now() - Duration::days(7)).naive_utc()));
+        assert_eq!(delete_in_batches(&db, condition, 2).await.unwrap(), 5);
+        assert_eq!(remaining_jobs().await.unwrap(), 5);
+        assert_eq!(remaining_attempts().await.unwrap(), 5);
+        assert!(
+            job::Entity::find()
+                .all(&db)
+                .await
+                .unwrap()
+                .iter()
+                .all(|job| kept.contains(&job.id)),
+            "Recent, failed and pending jobs are kept"
+        );
+
+        // The task applies each status's TTL in the same batches
+        let task = tokio::spawn(retention_task(
+            state.clone(),
+            RetentionConfig {
+                ttls: vec![(StatusEnum::Failure, Duration::days(7))],
+                batch_size: 2,
+                interval: std::time::Duration::from_secs(3600),
+            },
+        ));
+        for _ in 0..100 {
+            if remaining_jobs().await.unwrap() == 2 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        task.abort();
+        assert_eq!(remaining_jobs().await.unwrap(), 2);
+        assert_eq!(remaining_attempts().await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_bulk_retry_needs_a_filter_and_skips_cancelled_jobs() {
+        let state = test_state(test_db().await);
+        let failed = insert_job(&state.db, "http://a.test/1", StatusEnum::Failure).await;
+        let mut active = failed.clone().into_active_model();
+        active.scheduled_at = Set(Some(failed.next_run_at - Duration::hours(1)));
+        active.failure_reason = Set(Some(FailureReason::HttpStatus));
+        active.response_status = Set(Some(500));
+        active.response_headers = Set(Some(serde_json::json!({})));
+        active.response_body = Set(Some("err".to_string()));
+        active.response_truncated = Set(Some(false));
+        active.response_length = Set(Some(3));
+        let failed = active.update(&state.db).await.unwrap();
+        let cancelled = insert_job(&state.db, "http://a.test/2", StatusEnum::Cancelled).await;
+        let retry = |body| {
+            let request: BulkRetryRequest = serde_json::from_value(body).unwrap();
+            bulk_retry_jobs(State(state.clone()), ApiJson(request))
+        };
+        let status = |id| {
+            let db = state.db.clone();
+            async move { find_job(&db, id).await.unwrap().status }
+        };
+
+        let error = retry(serde_json::json!({"max_attempts": 3}))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(
+            status_of(error),
+            axum::http::StatusCode::UNPROCESSABLE_ENTITY
+        );
+
+        let retried = retry(serde_json::json!({"host": "a.test"})).await.unwrap();
+        assert_eq!(retried.retried, 1);
+        assert_eq!(status(failed.id).await, StatusEnum::Pending);
+        assert_eq!(status(cancelled.id).await, StatusEnum::Cancelled);
+
+        // The last outcome is cleared, and the new run gets a new webhook-id
+        let requeued = find_job(&state.db, failed.id).await.unwrap();
+        assert_eq!(requeued.scheduled_at, Some(requeued.next_run_at));
+        assert_ne!(webhook_id(&requeued), webhook_id(&failed));
+        assert_eq!(requeued.failure_reason, None);
+        assert_eq!(requeued.response_status, None);
+        assert_eq!(requeued.response_headers, None);
+        assert_eq!(requeued.response_body, None);
+        assert_eq!(requeued.response_truncated, None);
+        assert_eq!(requeued.response_length, None);
+
+        let retried = retry(serde_json::json!({"status": "Cancelled"}))
+            .await
+            .unwrap();
+        assert_eq!(retried.retried, 1);
+        assert_eq!(status(cancelled.id).await, StatusEnum::Pending);
+    }
+
+    #[tokio::test]
+    async fn test_delete_running_job_keeps_its_attempts() {
+        let state = test_state(test_db().await);
+        let running = insert_job(&state.db, "http://a.test/", StatusEnum::Running).await;
+        let failed = insert_job(&state.db, "http://b.test/", StatusEnum::Failure).await;
+        insert_attempt(&state.db, &running).await;
+        insert_attempt(&state.db, &failed).await;
+
+        let result = delete_job(State(state.clone()), ApiPath(running.id)).await;
+        assert!(result.is_err());
+        let attempts = job_attempt::Entity::find()
+            .filter(job_attempt::Column::JobId.eq(running.id))
+            .count(&state.db)
+            .await
+            .unwrap();
+        assert_eq!(attempts, 1);
+
+        let status = delete_job(State(state.clone()), ApiPath(failed.id))
+            .await
+            .unwrap();
+        assert_eq!(status, axum::http::StatusCode::NO_CONTENT);
+        let attempts = job_attempt::Entity::find()
+            .filter(job_attempt::Column::JobId.eq(failed.id))
+            .count(&state.db)
+            .await
+            .unwrap();
+        assert_eq!(attempts, 0);
+    }
+
+    #[tokio::test]
+    async fn test_attempt_history_is_newest_first() {
+        let state = test_state(test_db().await);
+        let mut job = insert_job(&state.db, "http://a.test/", StatusEnum::Failure).await;
+        let other = insert_job(&state.db, "http://b.test/", StatusEnum::Failure).await;
+        insert_attempt(&state.db, &other).await;
+        let start = Utc::now();
+        for n in 1..=3 {
+            job.attempts = n;
+            let started_at = start + Duration::seconds(n.into());
+            let outcome =
+                AttemptOutcome::not_sent("connection refused".to_string(), FailureReason::Connect);
+            attempt_model(&job, "worker", started_at, started_at, &outcome)
+                .insert(&state.db)
+                .await
+                .unwrap();
+        }
+        let page = |id, cursor| {
+            list_job_attempts(
+                State(state.clone()),
+                ApiPath(id),
+                ApiQuery(PageQuery {
+                    limit: Some(2),
+                    cursor,
+                }),
+            )
+        };
+
+        let first = page(job.id, None).await.unwrap();
+        let numbers: Vec<_> = first.attempts.iter().map(|a| a.attempt).collect();
+        assert_eq!(numbers, [3, 2]);
+        let second = page(job.id, first.next_cursor.clone()).await.unwrap();
+        let numbers: Vec<_> = second.attempts.iter().map(|a| a.attempt).collect();
+        assert_eq!(numbers, [1], "Only this job's attempts");
+        assert_eq!(second.next_cursor, None);
+
+        let error = page(Uuid::new_v4(), None).await.err().unwrap();
+        assert_eq!(status_of(error), axum::http::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_list_jobs_skips_payloads_it_cannot_decrypt() {
+        let mut state = test_state(test_db().await);
+        state.keyring = Arc::new(random_keyring("current"));
+        for (url, keyring) in [
+            ("http://a.test/removed", random_keyring("removed")),
+            ("http://a.test/current", (*state.keyring).clone()),
+        ] {
+            let request = job_request(serde_json::json!({
+                "url": url,
+                "method": "POST",
+                "headers": {"Authorization": "Bearer token"},
+            }));
+            let model = new_job_model(request, Utc::now().naive_utc(), &keyring);
+            model.insert(&state.db).await.unwrap();
+        }
+
+        let page = list_jobs(
+            State(state.clone()),
+            ApiQuery(JobFilter::default()),
+            ApiQuery(PageQuery {
+                limit: None,
+                cursor: None,
+            }),
+        )
+        .await
+        .unwrap();
+        let headers = |url: &str| {
+            let job = page.jobs.iter().find(|job| job.url == url).unwrap();
+            job.headers.clone()
+        };
+        assert_eq!(page.jobs.len(), 2);
+        assert_eq!(headers("http://a.test/removed"), JsonValue::Null);
+        assert_eq!(
+            headers("http://a.test/current"),
+            serde_json::json!({"Authorization": "Bearer token"})
+        );
+    }
+
+    /// The urls of the jobs `claim_jobs` hands out, one claim at a time
+    async fn claim_order(
+        db: &DatabaseConnection,
+        priority_aging: Option<std::time::Duration>,
+    ) -> Vec<String> {
+        let mut urls = Vec::new();
+        while let [job] = &claim_jobs(db, queues::DEFAULT, 1, priority_aging)
+            .await
+            .unwrap()[..]
+        {
+            urls.push(job.url.clone());
+        }
+        urls
+    }
+
+    #[tokio::test]
+    async fn test_claim_order_by_priority_and_due_time() {
+        for aging in [None, Some(std::time::Duration::from_secs(60))] {
+            let db = test_db().await;
+            let now = Utc::now().naive_utc();
+            // (url, priority, due this long ago)
+            let jobs = [
+                ("http://bulk-old.test/", -10, chrono::Duration::minutes(15)),
+                ("http://bulk-new.test/", -10, chrono::Duration::minutes(1)),
+                ("http://normal-old.test/", 0, chrono::Duration::minutes(3)),
+                ("http://normal-new.test/", 0, chrono::Duration::minutes(2)),
+                ("http://reset.test/", 10, chrono::Duration::seconds(5)),
+                ("http://later.test/", 100, chrono::Duration::minutes(-1)),
+            ];
+            for (url, priority, age) in jobs {
+                let job = insert_job(&db, url, StatusEnum::Pending).await;
+                let mut job: job::ActiveModel = job.into();
+                job.priority = Set(priority);
+                job.next_run_at = Set(now - age);
+                job.update(&db).await.unwrap();
+            }
+            insert_job(&db, "http://failed.test/", StatusEnum::Failure).await;
+
+            let expected = match aging {
+                // Priority first, then the job that has been due the longest
+                None => [
+                    "http://reset.test/",
+                    "http://normal-old.test/",
+                    "http://normal-new.test/",
+                    "http://bulk-old.test/",
+                    "http://bulk-new.test/",
+                ],
+                // With one level per minute of waiting, the bulk job due for 15
+                // minutes overtakes normal ones but not the job 10 levels up
+                Some(_) => [
+                    "http://reset.test/",
+                    "http://bulk-old.test/",
+                    "http://normal-old.test/",
+                    "http://normal-new.test/",
+                    "http://bulk-new.test/",
+                ],
+            };
+            assert_eq!(claim_order(&db, aging).await, expected, "aging {:?}", aging);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_releases_unfinished_jobs() {
+        use entity::sea_orm_active_enums::StatusEnum::*;
+        let state = test_state(test_db().await);
+        let db = state.db.clone();
+        let unfinished = insert_job(&db, "http://a.test/unfinished", Pending).await;
+        let cancelled = insert_job(&db, "http://a.test/cancelled", Pending).await;
+        let mut retried: job::ActiveModel = unfinished.into();
+        retried.retries = Set(2);
+        let unfinished = retried.update(&db).await.unwrap();
+
+        // Claimed by a worker that is then told to shut down
+        let claimed = claim_jobs(&db, queues::DEFAULT, 10, None).await.unwrap();
+        assert_eq!(claimed.len(), 2);
+        let cancelled = cancel_job(State(state), ApiPath(cancelled.id))
+            .await
+            .unwrap()
+            .0;
+        assert_eq!(
+            cancelled.status, Cancelled,
+            "Cancelled in place while running"
+        );
+
+        // Released jobs go back to Pending without using up the attempt, a
+        // cancellation made meanwhile is kept
+        let released: Vec<_> = claimed
+            .iter()
+            .map(|job| Completion::released(job.id, job.attempts))
+            .collect();
+        write_completions(&db, &released).await.unwrap();
+
+        let job = find_job(&db, unfinished.id).await.unwrap();
+        assert_eq!(job.status, Pending);
+        assert_eq!(job.attempts, 0);
+        assert_eq!(job.retries, 2, "Earlier retries are untouched");
+        assert_eq!(job.check_in, None);
+        assert_eq!(find_job(&db, cancelled.id).await.unwrap().status, Cancelled);
+        assert_eq!(
+            job_attempt::Entity::find().count(&db).await.unwrap(),
+            0,
+            "Nothing was sent, so no attempt is recorded"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_batch_claim_and_completion() {
+        use entity::sea_orm_active_enums::StatusEnum::*;
+        let state = test_state(test_db().await);
+        let db = state.db.clone();
+        for i in 0..5 {
+            insert_job(&db, &format!("http://a.test/{}", i), Pending).await;
+        }
+        let scheduled = insert_job(&db, "http://a.test/scheduled", Pending).await;
+        let mut later: job::ActiveModel = scheduled.into();
+        later.next_run_at = Set((Utc::now() + chrono::Duration::hours(1)).naive_utc());
+        later.update(&db).await.unwrap();
+
+        // One statement claims up to the number of free slots
+        let first = claim_jobs(&db, queues::DEFAULT, 3, None).await.unwrap();
+        assert_eq!(first.len(), 3);
+        assert!(
+            first
+                .iter()
+                .all(|job| job.status == Running && job.attempts == 1)
+        );
+        let second = claim_jobs(&db, queues::DEFAULT, 3, None).await.unwrap();
+        assert_eq!(
+            second.len(),
+            2,
+            "Claimed and scheduled jobs are not claimed again"
+        );
+
+        // Completions are written together, a cancelled job keeps its status
+        let claimed: Vec<_> = first.into_iter().chain(second).collect();
+        let _ = cancel_job(State(state), ApiPath(claimed[0].id))
+            .await
+            .unwrap();
+        let now = Utc::now();
+        let completions: Vec<_> = claimed
+            .iter()
+            .map(|job| Completion {
+                job_id: job.id,
+                attempt: Some(attempt_model(
+                    job,
+                    "worker",
+                    now,
+                    now,
+                    &AttemptOutcome::not_sent("refused".to_string(), FailureReason::Connect),
+                )),
+                update: job::ActiveModel {
+                    status: Set(Success),
+                    ..Default::default()
+                },
+            })
+            .collect();
+        write_completions(&db, &completions).await.unwrap();
+
+        let count = |status| {
+            job::Entity::find()
+                .filter(job::Column::Status.eq(status))
+                .count(&db)
+        };
+        assert_eq!(count(Success).await.unwrap(), 4);
+        assert_eq!(count(Cancelled).await.unwrap(), 1);
+        assert_eq!(count(Pending).await.unwrap(), 1);
+        assert_eq!(
+            job_attempt::Entity::find().count(&db).await.unwrap(),
+            5,
+            "The attempt of a cancelled job is still recorded"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_queues_are_claimed_and_paused_separately() {
+        use entity::sea_orm_active_enums::StatusEnum::*;
+        let db = test_db().await;
+        for i in 0..4 {
+            let job = insert_job(&db, &format!("http://analytics.test/{}", i), Pending).await;
+            let mut job: job::ActiveModel = job.into();
+            job.queue = Set("analytics".to_string());
+            job.update(&db).await.unwrap();
+        }
+        insert_job(&db, "http://reset.test/", Pending).await;
+
+        // A flood on one queue leaves the other queue's jobs alone
+        let analytics = claim_jobs(&db, "analytics", 10, None).await.unwrap();
+        assert_eq!(analytics.len(), 4);
+        let default = claim_jobs(&db, queues::DEFAULT, 10, None).await.unwrap();
+        let urls: Vec<_> = default.iter().map(|job| job.url.as_str()).collect();
+        assert_eq!(urls, ["http://reset.test/"]);
+
+        // Pausing is remembered per queue, resuming reuses the row
+        assert!(
+            !queues::is_paused(&db, queues::DEFAULT).await.unwrap(),
+            "Queues without a row are not paused"
+        );
+        let paused = queues::set_paused(&db, queues::DEFAULT, true)
+            .await
+            .unwrap();
+        assert!(paused.paused);
+        assert!(queues::is_paused(&db, queues::DEFAULT).await.unwrap());
+        assert!(!queues::is_paused(&db, "analytics").await.unwrap());
+
+        queues::set_paused(&db, queues::DEFAULT, false)
+            .await
+            .unwrap();
+        assert!(!queues::is_paused(&db, queues::DEFAULT).await.unwrap());
+        assert_eq!(queue::Entity::find().count(&db).await.unwrap(), 1);
+    }
+
+    #[test]
+    fn test_enqueue_validation_rules() {
+        let rejected = |value: JsonValue| -> Vec<String> {
+            match validate_job_request(&job_request(value), &SecretHosts::default()) {
+                Ok(()) => Vec::new(),
+                Err(errors) => errors.into_iter().map(|e| e.field).collect(),
+            }
+        };
+        let job = |extra: JsonValue| {
+            let mut value = serde_json::json!({"url": "https://a.test/hook", "method": "POST"});
+            value
+                .as_object_mut()
+                .unwrap()
+                .extend(extra.as_object().unwrap().clone());
+            value
+        };
+
+        assert!(rejected(job(serde_json::json!({}))).is_empty());
+        assert!(rejected(job(serde_json::json!({"cron": "0 */5 * * * *"}))).is_empty());
+
+        // Parses, but February 30th never comes
+        assert_eq!(
+            rejected(job(serde_json::json!({"cron": "0 0 0 30 2 *"}))),
+            ["cron"]
+        );
+
+        // Parse fine but cannot be delivered over HTTP
+        for url in [
+            "ftp://example.com/file",
+            "mailto:ops@example.com",
+            "file:///etc/passwd",
+        ] {
+            assert_eq!(
+                rejected(job(serde_json::json!({"url": url}))),
+                ["url"],
+                "{}",
+                url
+            );
+        }
+
+        // Only an object is a valid headers value
+        for headers in [
+            serde_json::json!(["Authorization", "Bearer token"]),
+            serde_json::json!("Authorization: Bearer token"),
+            serde_json::json!(1),
+        ] {
+            let fields = rejected(job(serde_json::json!({"headers": headers})));
+            assert_eq!(fields, ["headers"], "{}", headers);
+        }
+
+        // Every problem is reported at once
+        let fields = rejected(serde_json::json!({
+            "url": "https://{{secret:HOST}}/hook",
+            "method": "NOT A METHOD",
+            "timeout_ms": 0,
+            "priority": 101,
+        }));
+        assert_eq!(fields, ["method", "url", "timeout_ms", "priority"]);
+    }
+
+    fn test_config() -> WorkerConfig {
+        WorkerConfig {
+            retry: RetryConfig {
+                max_attempts: 3,
+                strategy: BackoffStrategy::Exponential,
+                base_delay_ms: 1000,
+                max_delay_ms: 3_600_000,
+                jitter: 0.0,
+                retryable_statuses: vec![408, 425, 429],
+                non_retryable_statuses: Vec::new(),
+                non_retryable_reasons: Vec::new(),
+            },
+            request_timeout: std::time::Duration::from_secs(5),
+            connect_timeout: std::time::Duration::from_secs(5),
+            response_body_limit: 1024,
+            claim_batch_size: 10,
+            priority_aging: None,
+            signing: SigningConfig::default(),
+            secrets: Arc::new(secrets::EnvProvider {
+                prefix: "BADGER_TEST_SECRET_".to_string(),
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_response_stored_apart_from_request_body() {
+        let mut state = test_state(test_db().await);
+        state.keyring = Arc::new(random_keyring("current"));
+        let addr = serve_once(
+            b"HTTP/1.1 200 OK\r\ncontent-type: text/plain\r\ncontent-length: 14\r\n\r\nplain response",
+        )
+        .await;
+        let request = job_request(serde_json::json!({
+            "url": format!("http://{}/tick", addr),
+            "method": "POST",
+            "body": {"event": "tick"},
+            "cron": "*/5 * * * * *",
+        }));
+        let model = new_job_model(request, Utc::now().naive_utc(), &state.keyring);
+        model.insert(&state.db).await.unwrap();
+
+        let [job] = &claim_jobs(&state.db, queues::DEFAULT, 1, None)
+            .await
+            .unwrap()[..]
+        else {
+            panic!("The job should be claimed");
+        };
+        let clients = HttpClients::new(std::time::Duration::from_secs(5)).unwrap();
+        let completion = execute_job(&state, &test_config(), &clients, "worker", job).await;
+        write_completions(&state.db, &[completion]).await.unwrap();
+
+        let stored = find_job(&state.db, job.id).await.unwrap();
+        assert_eq!(stored.status, StatusEnum::Pending, "The next cron run");
+        assert_eq!(stored.response_status, Some(200));
+        assert_eq!(stored.response_body.as_deref(), Some("plain response"));
+        assert_eq!(
+            stored.body, job.body,
+            "The request body is written back as it was"
+        );
+        assert_eq!(
+            state.keyring.decrypt(stored).unwrap().body,
+            serde_json::json!({"event": "tick"})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_job_pages_follow_the_cursor() {
+        let state = test_state(test_db().await);
+        for i in 0..5 {
+            insert_job(
+                &state.db,
+                &format!("http://a.test/{}", i),
+                StatusEnum::Pending,
+            )
+            .await;
+        }
+        insert_job(&state.db, "http://b.test/", StatusEnum::Pending).await;
+        let page = |cursor| {
+            let filter: JobFilter =
+                serde_json::from_value(serde_json::json!({"host": "a.test"})).unwrap();
+            list_jobs(
+                State(state.clone()),
+                ApiQuery(filter),
+                ApiQuery(PageQuery {
+                    limit: Some(2),
+                    cursor,
+                }),
+            )
+        };
+
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        loop {
+            let axum::Json(JobPage { jobs, next_cursor }) = page(cursor).await.unwrap();
+            assert!(jobs.len() <= 2);
+            seen.extend(jobs.into_iter().map(|job| (job.created_at, job.id)));
+            match next_cursor {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(seen.len(), 5, "Every job is listed once");
+        assert!(
+            seen.windows(2).all(|w| w[0] > w[1]),
+            "Newest first, without repeats"
+        );
+
+        let error = page(Some("not-a-cursor".to_string())).await.err().unwrap();
+        assert_eq!(status_of(error), axum::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_list_jobs_by_failure_reason() {
+        let state = test_state(test_db().await);
+        for (url, reason) in [
+            ("http://a.test/dns", Some(FailureReason::Dns)),
+            ("http://a.test/status", Some(FailureReason::HttpStatus)),
+            ("http://a.test/none", None),
+        ] {
+            let job = insert_job(&state.db, url, StatusEnum::Failure).await;
+            let mut active: job::ActiveModel = job.into();
+            active.failure_reason = Set(reason);
+            active.update(&state.db).await.unwrap();
+        }
+
+        let filter: JobFilter =
+            serde_json::from_value(serde_json::json!({"failure_reason": "dns"})).unwrap();
+        let page = list_jobs(
+            State(state.clone()),
+            ApiQuery(filter),
+            ApiQuery(PageQuery {
+                limit: None,
+                cursor: None,
+            }),
+        )
+        .await
+        .unwrap();
+        let urls: Vec<_> = page.jobs.iter().map(|job| job.url.as_str()).collect();
+        assert_eq!(urls, ["http://a.test/dns"]);
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let at = chrono::DateTime::from_timestamp_micros(1_760_000_000_123_456)
+            .unwrap()
+            .naive_utc();
+        let id = Uuid::new_v4();
+        assert_eq!(decode_cursor(&encode_cursor(at, id)), Some((at, id)));
+
+        for cursor in ["", "zz", &hex::encode("123"), &hex::encode("x|y")] {
+            assert_eq!(decode_cursor(cursor), None, "{:?}", cursor);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_secrets_only_go_to_their_hosts() {
+        let hosts = SecretHosts::parse("TOKEN=api.partner.test").unwrap();
+        let request = |url: &str| {
+            job_request(serde_json::json!({
+                "url": url,
+                "method": "POST",
+                "headers": {"Authorization": "Bearer {{secret:TOKEN}}"},
+            }))
+        };
+        assert!(validate_job_request(&request("https://api.partner.test/hook"), &hosts).is_ok());
+        let errors =
+            validate_job_request(&request("https://attacker.test/hook"), &hosts).unwrap_err();
+        let fields: Vec<_> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["headers.Authorization"]);
+
+        // A row edited by hand is stopped at dispatch
+        let db = test_db().await;
+        let mut job = insert_job(&db, "https://api.partner.test/hook", StatusEnum::Pending).await;
+        job.headers = serde_json::json!({"Authorization": "Bearer {{secret:TOKEN}}"});
+        let provider = secrets::EnvProvider {
+            prefix: "BADGER_TEST_SECRET_UNSET_".to_string(),
+        };
+        let error = resolve_secrets(&job, &provider, &hosts).unwrap_err();
+        assert_eq!(
+            error, "secret TOKEN is not defined",
+            "Allowed, then resolved"
+        );
+        job.url = "https://attacker.test/{{secret:TOKEN}}".to_string();
+        let error = resolve_secrets(&job, &provider, &hosts).unwrap_err();
+        assert_eq!(error, "secret TOKEN may not be sent to attacker.test");
+    }
+
+    async fn patch(state: &AppState, id: Uuid, value: JsonValue) -> Result<job::Model, ApiError> {
+        let request: UpdateJobRequest = serde_json::from_value(value).unwrap();
+        let updated = update_job(State(state.clone()), ApiPath(id), ApiJson(request)).await?;
+        Ok(updated.0)
+    }
+
+    #[tokio::test]
+    async fn test_update_keeps_or_rekeys_the_fingerprint() {
+        let state = test_state(test_db().await);
+        let job = insert_job(&state.db, "http://a.test/old", StatusEnum::Pending).await;
+        let mut attempted: job::ActiveModel = job.clone().into();
+        attempted.attempts = Set(2);
+        attempted.update(&state.db).await.unwrap();
+
+        let updated = patch(&state, job.id, serde_json::json!({"priority": 5}))
+            .await
+            .unwrap();
+        assert_eq!(updated.priority, 5);
+        assert_eq!(updated.unique_id, job.unique_id);
+
+        // Without `rekey` a changed URL keeps the original fingerprint
+        let updated = patch(
+            &state,
+            job.id,
+            serde_json::json!({"url": "http://a.test/new"}),
+        )
+        .await
+        .unwrap();
+        assert_eq!(updated.url, "http://a.test/new");
+        assert_eq!(updated.unique_id, job.unique_id);
+
+        let rekeyed = patch(
+            &state,
+            job.id,
+            serde_json::json!({"url": "http://b.test/", "rekey": true}),
+        )
+        .await
+        .unwrap();
+        assert_ne!(rekeyed.unique_id, job.unique_id);
+        assert_eq!((rekeyed.id, rekeyed.attempts), (job.id, 2));
+        assert_eq!(rekeyed.host.as_deref(), Some("b.test"));
+        assert_eq!(job::Entity::find().count(&state.db).await.unwrap(), 1);
+        assert_eq!(find_job(&state.db, job.id).await.unwrap(), rekeyed);
+    }
+
+    #[tokio::test]
+    async fn test_update_conflicts() {
+        let state = test_state(test_db().await);
+        let run_at = "2030-01-01T00:00:00Z";
+        let existing = new_job_model(
+            job_request(
+                serde_json::json!({"url": "http://b.test/", "method": "GET", "run_at": run_at}),
+            ),
+            Utc::now().naive_utc(),
+            &Keyring::default(),
+        );
+        existing.insert(&state.db).await.unwrap();
+        let job = insert_job(&state.db, "http://a.test/", StatusEnum::Pending).await;
+
+        // Re-keyed to the fingerprint of another job
+        let error = patch(
+            &state,
+            job.id,
+            serde_json::json!({"url": "http://b.test/", "run_at": run_at, "rekey": true}),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status_of(error), axum::http::StatusCode::CONFLICT);
+        assert_eq!(
+            find_job(&state.db, job.id).await.unwrap(),
+            job,
+            "Left as it was"
+        );
+
+        let running = insert_job(&state.db, "http://c.test/", StatusEnum::Running).await;
+        let error = patch(&state, running.id, serde_json::json!({"priority": 5}))
+            .await
+            .unwrap_err();
+        assert_eq!(status_of(error), axum::http::StatusCode::CONFLICT);
+        assert_eq!(find_job(&state.db, running.id).await.unwrap().priority, 0);
+    }
+
+    #[tokio::test]
+    async fn test_batch_results_follow_the_request() {
+        let state = test_state(test_db().await);
+        let item = |url: &str, method: &str| serde_json::json!({"url": url, "method": method, "run_at": "2030-01-01T00:00:00Z"});
+        let existing = new_job_model(
+            job_request(item("http://existing.test/", "GET")),
+            Utc::now().naive_utc(),
+            &Keyring::default(),
+        )
+        .insert(&state.db)
+        .await
+        .unwrap();
+
+        let items = vec![
+            item("http://a.test/", "GET"),
+            item("http://a.test/", "NOT A METHOD"),
+            item("http://a.test/", "GET"),
+            item("http://existing.test/", "GET"),
+            item("ftp://b.test/", "GET"),
+            item("http://b.test/", "GET"),
+        ];
+        let results = create_jobs_batch(
+            State(state.clone()),
+            ApiJson(items.into_iter().map(job_request).collect()),
+        )
+        .await
+        .unwrap()
+        .0;
+
+        let invalid_field = |result: &BatchItemResult| match result {
+            BatchItemResult::Invalid { error } => error.fields[0].field.clone(),
+            _ => panic!("The item should be invalid"),
+        };
+        let [
+            BatchItemResult::Created { id: first },
+            method,
+            BatchItemResult::Deduplicated { id: repeated },
+            BatchItemResult::Deduplicated { id: matched },
+            url,
+            BatchItemResult::Created { id: last },
+        ] = &results[..]
+        else {
+            panic!("Unexpected results");
+        };
+        assert_eq!(
+            repeated, first,
+            "A repeat points at the job created in this batch"
+        );
+        assert_eq!(*matched, existing.id);
+        assert_ne!(last, first);
+        assert_eq!(invalid_field(method), "method");
+        assert_eq!(invalid_field(url), "url");
+        assert_eq!(job::Entity::find().count(&state.db).await.unwrap(), 3);
+        assert_eq!(
+            find_job(&state.db, *last).await.unwrap().url,
+            "http://b.test/"
+        );
+    }
 }
@@ -0,0 +1,141 @@
+use crate::entity::queue;
+use crate::error::FieldError;
+use chrono::Utc;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{DatabaseConnection, DbErr, EntityTrait, Set};
+
+/// Queue of jobs submitted without one
+pub const DEFAULT: &str = "default";
+pub const MAX_NAME_LEN: usize = 64;
+
+/// Checks that a queue name is short and made of letters, digits, `-`, `_` and `.`
+pub fn validate(name: &str, field: &str, errors: &mut Vec<FieldError>) {
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        errors.push(FieldError::new(
+            field,
+            format!("queue must be 1 to {} characters long", MAX_NAME_LEN),
+        ));
+    } else if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        errors.push(FieldError::new(
+            field,
+            "queue may only contain letters, digits, '-', '_' and '.'",
+        ));
+    }
+}
+
+/// Parses the queues a process serves, as `name=concurrency` pairs separated by
+/// commas, such as `default=10,emails=5`. A name without a concurrency gets
+/// `default_concurrency`, and an empty list serves the default queue only.
+pub fn parse(list: &str, default_concurrency: usize) -> Result<Vec<(String, usize)>, String> {
+    let mut queues: Vec<(String, usize)> = Vec::new();
+
+    for entry in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (name, concurrency) = match entry.split_once('=') {
+            Some((name, concurrency)) => {
+                let concurrency = concurrency
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| {
+                        format!("concurrency for {} must be a positive number", name.trim())
+                    })?;
+                (name.trim(), concurrency)
+            }
+            None => (entry, default_concurrency),
+        };
+
+        let mut errors = Vec::new();
+        validate(name, "queue", &mut errors);
+        if let Some(error) = errors.first() {
+            return Err(format!("{:?}: {}", name, error.message));
+        }
+        if queues.iter().any(|(existing, _)| existing == name) {
+            return Err(format!("queue {} is listed twice", name));
+        }
+        queues.push((name.to_string(), concurrency));
+    }
+
+    if queues.is_empty() {
+        queues.push((DEFAULT.to_string(), default_concurrency));
+    }
+    Ok(queues)
+}
+
+/// Whether dispatchers should stop claiming from a queue. Queues without a row
+/// have never been paused.
+pub async fn is_paused(db: &DatabaseConnection, name: &str) -> Result<bool, DbErr> {
+    Ok(queue::Entity::find_by_id(name)
+        .one(db)
+        .await?
+        .is_some_and(|q| q.paused))
+}
+
+/// Pauses or resumes a queue, creating its row on first use
+pub async fn set_paused(
+    db: &DatabaseConnection,
+    name: &str,
+    paused: bool,
+) -> Result<queue::Model, DbErr> {
+    let model = queue::ActiveModel {
+        name: Set(name.to_string()),
+        paused: Set(paused),
+        updated_at: Set(Utc::now().naive_utc()),
+    };
+
+    queue::Entity::insert(model)
+        .on_conflict(
+            OnConflict::column(queue::Column::Name)
+                .update_columns([queue::Column::Paused, queue::Column::UpdatedAt])
+                .to_owned(),
+        )
+        .exec_with_returning(db)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn served(list: &str) -> Vec<(String, usize)> {
+        parse(list, 10).unwrap()
+    }
+
+    #[test]
+    fn test_parse_queue_list() {
+        assert_eq!(served(""), [("default".to_string(), 10)]);
+        assert_eq!(served(" , "), [("default".to_string(), 10)]);
+        assert_eq!(
+            served("default=10, emails = 5,analytics"),
+            [
+                ("default".to_string(), 10),
+                ("emails".to_string(), 5),
+                ("analytics".to_string(), 10),
+            ]
+        );
+        assert_eq!(
+            served("emails=2"),
+            [("emails".to_string(), 2)],
+            "Listing queues replaces the default one"
+        );
+    }
+
+    #[test]
+    fn test_parse_queue_list_errors() {
+        let error = |list| parse(list, 10).unwrap_err();
+        assert_eq!(
+            error("emails=0"),
+            "concurrency for emails must be a positive number"
+        );
+        assert_eq!(
+            error("emails=lots"),
+            "concurrency for emails must be a positive number"
+        );
+        assert_eq!(error("emails,emails=2"), "queue emails is listed twice");
+        assert!(error("e mails").contains("may only contain"));
+        assert!(error(&format!("{}=1", "q".repeat(MAX_NAME_LEN + 1))).contains("1 to 64"));
+    }
+}
@@ -0,0 +1,145 @@
+use encoding_rs::{Encoding, UTF_8};
+
+/// The part of a response body the worker keeps
+#[derive(Default)]
+pub struct CapturedBody {
+    pub text: String,
+    /// `text` is not the whole body, it was longer than the capture limit or
+    /// could not be read to the end
+    pub truncated: bool,
+    /// Size of the whole body in bytes, from `Content-Length` or from what was read.
+    /// `None` if the body was cut off and the target did not announce its size.
+    pub length: Option<i64>,
+}
+
+/// Reads at most `limit` bytes of the body. The rest is never buffered: the response is
+/// dropped instead, which closes the connection rather than returning it to the pool.
+/// If reading fails, what was read so far is kept along with the error.
+pub async fn capture(
+    mut resp: reqwest::Response,
+    limit: usize,
+) -> (CapturedBody, Option<reqwest::Error>) {
+    let announced = resp.content_length();
+    let encoding = charset(resp.headers());
+
+    let mut bytes = Vec::new();
+    let mut truncated = false;
+    let mut error = None;
+    loop {
+        let chunk = match resp.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(e) => {
+                truncated = true;
+                error = Some(e);
+                break;
+            }
+        };
+        let room = limit - bytes.len();
+        if chunk.len() > room {
+            bytes.extend_from_slice(&chunk[..room]);
+            truncated = true;
+            break;
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    drop(resp);
+
+    let length = match (announced, truncated) {
+        (Some(length), _) => Some(length as i64),
+        (None, false) => Some(bytes.len() as i64),
+        (None, true) => None,
+    };
+
+    let captured = CapturedBody {
+        text: decode(&bytes, encoding, truncated),
+        truncated,
+        length,
+    };
+    (captured, error)
+}
+
+/// Encoding named by the `charset` parameter of `Content-Type`, UTF-8 otherwise
+fn charset(headers: &reqwest::header::HeaderMap) -> &'static Encoding {
+    headers
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| {
+            value.split(';').skip(1).find_map(|param| {
+                let (name, label) = param.split_once('=')?;
+                name.trim()
+                    .eq_ignore_ascii_case("charset")
+                    .then(|| label.trim().trim_matches('"'))
+            })
+        })
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+        .unwrap_or(UTF_8)
+}
+
+/// Decodes `bytes`, dropping a character cut in half by truncation
+/// rather than turning it into U+FFFD
+fn decode(bytes: &[u8], encoding: &'static Encoding, truncated: bool) -> String {
+    let mut decoder = encoding.new_decoder();
+    let mut text = String::with_capacity(
+        decoder
+            .max_utf8_buffer_length(bytes.len())
+            .unwrap_or(bytes.len()),
+    );
+    let _ = decoder.decode_to_string(bytes, &mut text, !truncated);
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn capture_body(
+        body: &'static [u8],
+        content_type: Option<&str>,
+        limit: usize,
+    ) -> CapturedBody {
+        let mut builder = axum::http::Response::builder();
+        if let Some(content_type) = content_type {
+            builder = builder.header(reqwest::header::CONTENT_TYPE, content_type);
+        }
+        let resp = reqwest::Response::from(builder.body(body).unwrap());
+        let (captured, error) = capture(resp, limit).await;
+        assert!(error.is_none());
+        captured
+    }
+
+    #[tokio::test]
+    async fn test_capture_limit() {
+        let whole = capture_body(b"hello world", None, 1024).await;
+        assert_eq!(whole.text, "hello world");
+        assert!(!whole.truncated);
+        assert_eq!(whole.length, Some(11));
+
+        let cut = capture_body(b"hello world", None, 8).await;
+        assert_eq!(cut.text, "hello wo");
+        assert!(cut.truncated);
+
+        // A character cut in half by the limit is dropped, not replaced
+        let cut = capture_body("añb".as_bytes(), None, 2).await;
+        assert_eq!(cut.text, "a");
+        assert!(cut.truncated);
+    }
+
+    #[tokio::test]
+    async fn test_capture_charset() {
+        let latin1 = capture_body(b"caf\xe9", Some("text/plain; charset=iso-8859-1"), 1024).await;
+        assert_eq!(latin1.text, "café", "Latin-1 is not UTF-8");
+        let quoted = capture_body(
+            b"\x82\xa0",
+            Some(r#"text/plain; Charset="Shift_JIS""#),
+            1024,
+        )
+        .await;
+        assert_eq!(quoted.text, "あ");
+        let unknown = capture_body("café".as_bytes(), Some("text/plain; charset=nope"), 1024).await;
+        assert_eq!(
+            unknown.text, "café",
+            "UTF-8 unless a known charset is named"
+        );
+    }
+}
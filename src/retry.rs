@@ -0,0 +1,412 @@
+use crate::entity::job;
+use crate::entity::sea_orm_active_enums::FailureReason;
+use crate::error::FieldError;
+use chrono::{DateTime, Duration, Utc};
+use rand::RngExt;
+use reqwest::StatusCode;
+use sea_orm::Iterable;
+use serde_json::Value as JsonValue;
+
+/// Upper bound for any configured delay: 30 days
+pub const MAX_DELAY_MS: u64 = 30 * 24 * 60 * 60 * 1000;
+
+/// How the delay grows between attempts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackoffStrategy {
+    /// `base_delay_ms` before every retry
+    Fixed,
+    /// `base_delay_ms * n` before retry `n`
+    Linear,
+    /// `base_delay_ms * 2^(n-1)` before retry `n`
+    Exponential,
+}
+
+impl std::str::FromStr for BackoffStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "fixed" => Ok(Self::Fixed),
+            "linear" => Ok(Self::Linear),
+            "exponential" => Ok(Self::Exponential),
+            other => Err(format!("unknown backoff strategy: {}", other)),
+        }
+    }
+}
+
+/// Retry policy as submitted with a job. Unset fields use the server defaults.
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct RetryPolicy {
+    // Kept in the job's `max_attempts` column, which the retry endpoints also set
+    #[serde(skip_serializing)]
+    pub max_attempts: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<BackoffStrategy>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_delay_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_delay_ms: Option<u64>,
+    /// Fraction of the delay added or removed at random, between 0 and 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub jitter: Option<f64>,
+    /// Statuses retried even though they are 4xx
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retryable_statuses: Option<Vec<u16>>,
+    /// Statuses that fail the job right away
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub non_retryable_statuses: Option<Vec<u16>>,
+    /// Failure reasons that fail the job right away, e.g. `["dns", "tls"]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub non_retryable_reasons: Option<Vec<FailureReason>>,
+}
+
+impl RetryPolicy {
+    /// Rebuilds the policy stored on a job
+    pub fn from_job(job: &job::Model) -> Self {
+        let mut policy: Self = job
+            .retry_policy
+            .clone()
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default();
+        policy.max_attempts = job.max_attempts;
+        policy
+    }
+
+    /// Value for the `retry_policy` column, `None` when only defaults are used
+    pub fn to_json(&self) -> Option<JsonValue> {
+        let value = serde_json::to_value(self).ok()?;
+        match value.as_object() {
+            Some(map) if map.is_empty() => None,
+            _ => Some(value),
+        }
+    }
+
+    pub fn validate(&self, errors: &mut Vec<FieldError>) {
+        if self.max_attempts.is_some_and(|n| n < 1) {
+            errors.push(FieldError::new(
+                "retry.max_attempts",
+                "max_attempts must be at least 1",
+            ));
+        }
+        for (field, value) in [
+            ("retry.base_delay_ms", self.base_delay_ms),
+            ("retry.max_delay_ms", self.max_delay_ms),
+        ] {
+            if value.is_some_and(|ms| ms > MAX_DELAY_MS) {
+                errors.push(FieldError::new(
+                    field,
+                    format!("delay must not exceed {} ms", MAX_DELAY_MS),
+                ));
+            }
+        }
+        if let (Some(base), Some(max)) = (self.base_delay_ms, self.max_delay_ms)
+            && max < base
+        {
+            errors.push(FieldError::new(
+                "retry.max_delay_ms",
+                "max_delay_ms must not be less than base_delay_ms",
+            ));
+        }
+        if self.jitter.is_some_and(|j| !(0.0..=1.0).contains(&j)) {
+            errors.push(FieldError::new(
+                "retry.jitter",
+                "jitter must be between 0 and 1",
+            ));
+        }
+        for (field, statuses) in [
+            ("retry.retryable_statuses", &self.retryable_statuses),
+            ("retry.non_retryable_statuses", &self.non_retryable_statuses),
+        ] {
+            if let Some(invalid) = statuses
+                .iter()
+                .flatten()
+                .find(|code| !(100..=599).contains(*code))
+            {
+                errors.push(FieldError::new(
+                    field,
+                    format!("invalid HTTP status: {}", invalid),
+                ));
+            }
+        }
+    }
+}
+
+/// A fully resolved retry policy: the server defaults, or a job's policy applied on top of them
+#[derive(Debug, Clone)]
+pub struct RetryConfig {
+    pub max_attempts: i32,
+    pub strategy: BackoffStrategy,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub jitter: f64,
+    pub retryable_statuses: Vec<u16>,
+    pub non_retryable_statuses: Vec<u16>,
+    pub non_retryable_reasons: Vec<FailureReason>,
+}
+
+impl RetryConfig {
+    pub fn for_job(&self, job: &job::Model) -> Self {
+        let policy = RetryPolicy::from_job(job);
+        Self {
+            max_attempts: policy.max_attempts.unwrap_or(self.max_attempts),
+            strategy: policy.strategy.unwrap_or(self.strategy),
+            base_delay_ms: policy.base_delay_ms.unwrap_or(self.base_delay_ms),
+            max_delay_ms: policy.max_delay_ms.unwrap_or(self.max_delay_ms),
+            jitter: policy.jitter.unwrap_or(self.jitter),
+            retryable_statuses: policy
+                .retryable_statuses
+                .unwrap_or_else(|| self.retryable_statuses.clone()),
+            non_retryable_statuses: policy
+                .non_retryable_statuses
+                .unwrap_or_else(|| self.non_retryable_statuses.clone()),
+            non_retryable_reasons: policy
+                .non_retryable_reasons
+                .unwrap_or_else(|| self.non_retryable_reasons.clone()),
+        }
+    }
+
+    /// Client errors fail right away, everything else is retried,
+    /// unless the status is listed as an exception
+    pub fn is_retryable(&self, status: StatusCode) -> bool {
+        let code = status.as_u16();
+        if self.non_retryable_statuses.contains(&code) {
+            return false;
+        }
+        if self.retryable_statuses.contains(&code) {
+            return true;
+        }
+        !status.is_client_error()
+    }
+
+    /// Whether a failed attempt is worth another try. Transport failures are
+    /// retried unless their reason is excluded, answered requests go by status.
+    pub fn should_retry(&self, reason: FailureReason, status: Option<StatusCode>) -> bool {
+        if self.non_retryable_reasons.contains(&reason) {
+            return false;
+        }
+        status.is_none_or(|status| self.is_retryable(status))
+    }
+
+    /// Delay the target asked for with `Retry-After`, bounded by `max_delay_ms`
+    pub fn retry_after(&self, requested: Duration) -> Duration {
+        requested.clamp(
+            Duration::zero(),
+            Duration::milliseconds(self.max_delay_ms.min(MAX_DELAY_MS) as i64),
+        )
+    }
+
+    /// Delay before the next run after `attempt` (1-based) failed, capped at `max_delay_ms`
+    pub fn delay(&self, attempt: i32) -> Duration {
+        let n = attempt.max(1) as u64;
+        let delay = match self.strategy {
+            BackoffStrategy::Fixed => self.base_delay_ms,
+            BackoffStrategy::Linear => self.base_delay_ms.saturating_mul(n),
+            BackoffStrategy::Exponential => 2u64
+                .checked_pow((n - 1).min(u32::MAX as u64) as u32)
+                .map_or(u64::MAX, |factor| self.base_delay_ms.saturating_mul(factor)),
+        }
+        .min(self.max_delay_ms)
+        .min(MAX_DELAY_MS);
+
+        // Jitter prevents thundering herd when many jobs fail at once
+        let factor = if self.jitter > 0.0 {
+            1.0 + rand::rng().random_range(-self.jitter..=self.jitter)
+        } else {
+            1.0
+        };
+        let delay = (delay as f64 * factor).min(self.max_delay_ms as f64) as i64;
+
+        Duration::milliseconds(delay.max(0))
+    }
+}
+
+/// Parses a comma separated list of status codes such as `408,425,429`
+pub fn parse_statuses(list: &str) -> Vec<u16> {
+    list.split(',')
+        .filter_map(|code| code.trim().parse().ok())
+        .filter(|code| (100..=599).contains(code))
+        .collect()
+}
+
+/// Parses a comma separated list of failure reasons such as `dns,tls`
+pub fn parse_reasons(list: &str) -> Vec<FailureReason> {
+    list.split(',')
+        .filter_map(|name| {
+            let name = name.trim().to_ascii_lowercase();
+            FailureReason::iter().find(|reason| reason.as_str() == name)
+        })
+        .collect()
+}
+
+/// Reads a `Retry-After` value, either delay-seconds or an HTTP-date
+pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u32>() {
+        return Some(Duration::seconds(seconds as i64));
+    }
+    // IMF-fixdate, e.g. "Wed, 21 Oct 2015 07:28:00 GMT", is valid RFC 2822
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some((date.with_timezone(&Utc) - now).max(Duration::zero()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(strategy: BackoffStrategy) -> RetryConfig {
+        RetryConfig {
+            max_attempts: 5,
+            strategy,
+            base_delay_ms: 1000,
+            max_delay_ms: 3_600_000,
+            jitter: 0.0,
+            retryable_statuses: parse_statuses("408,425,429"),
+            non_retryable_statuses: Vec::new(),
+            non_retryable_reasons: Vec::new(),
+        }
+    }
+
+    fn status(code: u16) -> Option<StatusCode> {
+        Some(StatusCode::from_u16(code).unwrap())
+    }
+
+    #[test]
+    fn test_status_retry_classification() {
+        let mut retry = config(BackoffStrategy::Exponential);
+        for code in [408, 425, 429, 500, 502, 503, 504] {
+            assert!(
+                retry.should_retry(FailureReason::HttpStatus, status(code)),
+                "{} should be retried",
+                code
+            );
+        }
+        for code in [400, 401, 403, 404, 409, 422] {
+            assert!(
+                !retry.should_retry(FailureReason::HttpStatus, status(code)),
+                "{} should fail immediately",
+                code
+            );
+        }
+
+        retry.non_retryable_statuses = vec![501];
+        retry.retryable_statuses = vec![404];
+        assert!(!retry.should_retry(FailureReason::HttpStatus, status(501)));
+        assert!(retry.should_retry(FailureReason::HttpStatus, status(404)));
+    }
+
+    #[test]
+    fn test_reason_retry_classification() {
+        let mut retry = config(BackoffStrategy::Exponential);
+        for reason in [
+            FailureReason::Dns,
+            FailureReason::Connect,
+            FailureReason::Tls,
+            FailureReason::Timeout,
+        ] {
+            assert!(
+                retry.should_retry(reason, None),
+                "{:?} should be retried",
+                reason
+            );
+        }
+
+        // An unreadable body goes by the status that came with it
+        assert!(retry.should_retry(FailureReason::BodyRead, status(503)));
+        assert!(!retry.should_retry(FailureReason::BodyRead, status(404)));
+
+        retry.non_retryable_reasons = vec![FailureReason::Dns, FailureReason::HttpStatus];
+        assert!(!retry.should_retry(FailureReason::Dns, None));
+        assert!(!retry.should_retry(FailureReason::HttpStatus, status(503)));
+        assert!(retry.should_retry(FailureReason::Connect, None));
+    }
+
+    #[test]
+    fn test_retry_after_parsing() {
+        let now = DateTime::parse_from_rfc3339("2015-10-21T07:27:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(parse_retry_after("120", now), Some(Duration::seconds(120)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now),
+            Some(Duration::seconds(60))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::zero()),
+            "Past dates mean retry now"
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+        assert_eq!(parse_retry_after("-5", now), None);
+
+        let mut retry = config(BackoffStrategy::Fixed);
+        retry.max_delay_ms = 60_000;
+        assert_eq!(
+            retry.retry_after(Duration::hours(1)),
+            Duration::seconds(60),
+            "Retry-After is capped at max_delay_ms"
+        );
+    }
+
+    #[test]
+    fn test_backoff_strategies() {
+        let fixed = config(BackoffStrategy::Fixed);
+        let linear = config(BackoffStrategy::Linear);
+        let exponential = config(BackoffStrategy::Exponential);
+        for attempt in 1..=6 {
+            assert_eq!(fixed.delay(attempt), Duration::seconds(1));
+            assert_eq!(linear.delay(attempt), Duration::seconds(attempt as i64));
+            assert_eq!(
+                exponential.delay(attempt),
+                Duration::seconds(1 << (attempt - 1))
+            );
+        }
+        assert_eq!(
+            exponential.delay(0),
+            Duration::seconds(1),
+            "Attempts start at 1"
+        );
+    }
+
+    #[test]
+    fn test_backoff_is_capped_at_max_delay() {
+        let mut linear = config(BackoffStrategy::Linear);
+        linear.max_delay_ms = 10_000;
+        assert_eq!(linear.delay(30), Duration::seconds(10));
+
+        let exponential = config(BackoffStrategy::Exponential);
+        // 2^79 overflows, the cap still holds
+        for attempt in [20, 80, i32::MAX] {
+            assert_eq!(exponential.delay(attempt), Duration::hours(1));
+        }
+
+        let mut unbounded = config(BackoffStrategy::Exponential);
+        unbounded.max_delay_ms = u64::MAX;
+        assert_eq!(
+            unbounded.delay(80),
+            Duration::milliseconds(MAX_DELAY_MS as i64),
+            "No delay exceeds MAX_DELAY_MS"
+        );
+    }
+
+    #[test]
+    fn test_jitter_stays_within_bounds() {
+        let mut retry = config(BackoffStrategy::Fixed);
+        retry.base_delay_ms = 10_000;
+        retry.jitter = 0.2;
+        let delays: Vec<_> = (0..200).map(|_| retry.delay(1)).collect();
+        assert!(
+            delays
+                .iter()
+                .all(|d| (8_000..=12_000).contains(&d.num_milliseconds()))
+        );
+        assert!(
+            delays.iter().any(|d| *d != delays[0]),
+            "Jitter should vary the delay"
+        );
+
+        // Jitter never pushes a delay past max_delay_ms
+        retry.max_delay_ms = 10_000;
+        assert!((0..200).all(|_| retry.delay(1) <= Duration::seconds(10)));
+    }
+}
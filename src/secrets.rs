@@ -0,0 +1,430 @@
+use crate::crypto::SecretKey;
+use crate::error::FieldError;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+use url::Url;
+
+const OPEN: &str = "{{secret:";
+const CLOSE: &str = "}}";
+
+/// Where `{{secret:NAME}}` placeholders get their values. Providers are asked on every
+/// dispatch, so a rotated secret applies to every job that has not run yet.
+pub trait SecretProvider: std::fmt::Debug + Send + Sync {
+    /// `Ok(None)` when the secret does not exist
+    fn get(&self, name: &str) -> Result<Option<String>, String>;
+}
+
+/// Reads `NAME` from the environment variable `<prefix>NAME`. The prefix keeps
+/// placeholders from reaching unrelated variables such as `DATABASE_URL`.
+#[derive(Debug)]
+pub struct EnvProvider {
+    pub prefix: String,
+}
+
+impl SecretProvider for EnvProvider {
+    fn get(&self, name: &str) -> Result<Option<String>, String> {
+        Ok(std::env::var(format!("{}{}", self.prefix, name)).ok())
+    }
+}
+
+/// Reads `NAME` from the file `<dir>/NAME`, the layout of Docker and Kubernetes secrets
+#[derive(Debug)]
+pub struct DirectoryProvider {
+    pub dir: PathBuf,
+}
+
+impl SecretProvider for DirectoryProvider {
+    fn get(&self, name: &str) -> Result<Option<String>, String> {
+        match std::fs::read_to_string(self.dir.join(name)) {
+            // Files written by hand usually end with a newline that is not part of the secret
+            Ok(value) => Ok(Some(value.trim_end_matches(['\r', '\n']).to_string())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("cannot read secret {}: {}", name, e)),
+        }
+    }
+}
+
+/// A JSON file mapping names to AES-256-GCM encrypted values, managed with
+/// `badger secret set` and `badger secret delete`
+#[derive(Debug)]
+pub struct StoreProvider {
+    pub path: PathBuf,
+    pub key: SecretKey,
+}
+
+impl StoreProvider {
+    fn load(&self) -> Result<BTreeMap<String, String>, String> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| format!("{} is not a secret store: {}", self.path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(format!("cannot read {}: {}", self.path.display(), e)),
+        }
+    }
+
+    fn save(&self, store: &BTreeMap<String, String>) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(store).map_err(|e| e.to_string())?;
+        // Written next to the store and renamed, so readers never see half a file
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, text)
+            .and_then(|_| std::fs::rename(&tmp, &self.path))
+            .map_err(|e| format!("cannot write {}: {}", self.path.display(), e))
+    }
+
+    pub fn set(&self, name: &str, value: &str) -> Result<(), String> {
+        let mut store = self.load()?;
+        // The name is bound to the value, so entries cannot be swapped in the file
+        store.insert(
+            name.to_string(),
+            self.key.seal(value.as_bytes(), name.as_bytes()),
+        );
+        self.save(&store)
+    }
+
+    pub fn delete(&self, name: &str) -> Result<bool, String> {
+        let mut store = self.load()?;
+        let existed = store.remove(name).is_some();
+        self.save(&store)?;
+        Ok(existed)
+    }
+}
+
+impl SecretProvider for StoreProvider {
+    fn get(&self, name: &str) -> Result<Option<String>, String> {
+        let Some(sealed) = self.load()?.remove(name) else {
+            return Ok(None);
+        };
+        let value = self
+            .key
+            .open(&sealed, name.as_bytes())
+            .map_err(|e| format!("secret {}: {}", name, e))?;
+        String::from_utf8(value)
+            .map(Some)
+            .map_err(|_| format!("secret {} is not UTF-8", name))
+    }
+}
+
+/// Builds the provider selected by `SECRET_PROVIDER`: `env` (default), `dir` or `store`
+pub fn provider_from_env() -> Result<Arc<dyn SecretProvider>, String> {
+    let kind = std::env::var("SECRET_PROVIDER").unwrap_or_else(|_| "env".to_string());
+    match kind.as_str() {
+        "env" => Ok(Arc::new(EnvProvider {
+            prefix: std::env::var("SECRET_ENV_PREFIX")
+                .unwrap_or_else(|_| "BADGER_SECRET_".to_string()),
+        })),
+        "dir" => Ok(Arc::new(DirectoryProvider {
+            dir: std::env::var("SECRET_DIR")
+                .map_err(|_| "SECRET_PROVIDER=dir needs SECRET_DIR".to_string())?
+                .into(),
+        })),
+        "store" => store_from_env().map(|store| Arc::new(store) as Arc<dyn SecretProvider>),
+        other => Err(format!("unknown SECRET_PROVIDER: {}", other)),
+    }
+}
+
+/// The local store configured by `SECRET_STORE_PATH` and `SECRET_STORE_KEY`
+pub fn store_from_env() -> Result<StoreProvider, String> {
+    let path = std::env::var("SECRET_STORE_PATH").unwrap_or_else(|_| "secrets.json".to_string());
+    let key = std::env::var("SECRET_STORE_KEY")
+        .map_err(|_| "the secret store needs SECRET_STORE_KEY".to_string())?;
+    Ok(StoreProvider {
+        path: path.into(),
+        key: SecretKey::from_base64(&key).map_err(|e| format!("SECRET_STORE_KEY: {}", e))?,
+    })
+}
+
+/// Names are limited to what is safe as an environment variable suffix or a file name
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+/// Splits `text` into literal parts and placeholder names
+fn parse(text: &str) -> Result<Vec<(&str, Option<&str>)>, String> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(OPEN) {
+        let after = &rest[start + OPEN.len()..];
+        let end = after
+            .find(CLOSE)
+            .ok_or_else(|| "unterminated {{secret:...}} placeholder".to_string())?;
+        let name = &after[..end];
+        if !is_valid_name(name) {
+            return Err(format!("invalid secret name: {:?}", name));
+        }
+        parts.push((&rest[..start], Some(name)));
+        rest = &after[end + CLOSE.len()..];
+    }
+    parts.push((rest, None));
+    Ok(parts)
+}
+
+/// Reports malformed placeholders in `text` under `field`
+pub fn validate(text: &str, field: &str, errors: &mut Vec<FieldError>) {
+    if let Err(message) = parse(text) {
+        errors.push(FieldError::new(field, message));
+    }
+}
+
+/// `text` with every placeholder replaced by a fixed value, for checks made before dispatch
+pub fn mask(text: &str, value: &str) -> String {
+    match parse(text) {
+        Ok(parts) => parts
+            .into_iter()
+            .map(|(literal, name)| format!("{}{}", literal, name.map_or("", |_| value)))
+            .collect(),
+        Err(_) => text.to_string(),
+    }
+}
+
+/// `text` with every placeholder replaced by its secret
+pub fn resolve(text: &str, provider: &dyn SecretProvider) -> Result<String, String> {
+    let mut resolved = String::with_capacity(text.len());
+    for (literal, name) in parse(text)? {
+        resolved.push_str(literal);
+        if let Some(name) = name {
+            let value = provider
+                .get(name)?
+                .ok_or_else(|| format!("secret {} is not defined", name))?;
+            resolved.push_str(&value);
+        }
+    }
+    Ok(resolved)
+}
+
+/// The hosts each secret may be sent to. A secret without hosts is never sent, so a
+/// job cannot carry it to a server its submitter controls.
+#[derive(Debug, Clone, Default)]
+pub struct SecretHosts(HashMap<String, Vec<String>>);
+
+impl SecretHosts {
+    /// Reads `SECRET_HOSTS`
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(&std::env::var("SECRET_HOSTS").unwrap_or_default())
+    }
+
+    /// Parses `NAME=host` pairs separated by commas. A name listed more than once may
+    /// be sent to each of its hosts.
+    pub fn parse(list: &str) -> Result<Self, String> {
+        let mut hosts = Self::default();
+        for entry in list.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (name, host) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("expected NAME=host, got {:?}", entry))?;
+            let name = name.trim();
+            if !is_valid_name(name) {
+                return Err(format!("invalid secret name: {:?}", name));
+            }
+            // Normalized the way job URLs are, so the comparison is exact
+            let host = Url::parse(&format!("http://{}/", host.trim()))
+                .ok()
+                .filter(|url| url.port().is_none() && url.path() == "/")
+                .and_then(|url| url.host_str().map(str::to_string))
+                .ok_or_else(|| format!("invalid host for secret {}: {:?}", name, host))?;
+            hosts.0.entry(name.to_string()).or_default().push(host);
+        }
+        Ok(hosts)
+    }
+
+    /// Checks that every secret referenced in `text` may be sent to `host`
+    pub fn check(&self, text: &str, host: Option<&str>) -> Result<(), String> {
+        // Malformed placeholders are reported by `validate` and never resolve
+        let Ok(parts) = parse(text) else {
+            return Ok(());
+        };
+        for name in parts.into_iter().filter_map(|(_, name)| name) {
+            let allowed = host.is_some_and(|host| {
+                self.0
+                    .get(name)
+                    .is_some_and(|hosts| hosts.iter().any(|allowed| allowed == host))
+            });
+            if !allowed {
+                return Err(format!(
+                    "secret {} may not be sent to {}",
+                    name,
+                    host.unwrap_or("this URL")
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Handles `badger secret set NAME` (value read from stdin) and `badger secret delete NAME`
+pub fn run_command(args: &[String]) -> Result<(), String> {
+    let usage = "usage: badger secret set NAME < value | badger secret delete NAME";
+    let (action, name) = match args {
+        [action, name] => (action.as_str(), name.as_str()),
+        _ => return Err(usage.to_string()),
+    };
+    if !is_valid_name(name) {
+        return Err(format!("invalid secret name: {:?}", name));
+    }
+    let store = store_from_env()?;
+    match action {
+        "set" => {
+            let mut value = String::new();
+            std::io::Read::read_to_string(&mut std::io::stdin(), &mut value)
+                .map_err(|e| format!("cannot read the value from stdin: {}", e))?;
+            store.set(name, value.trim_end_matches(['\r', '\n']))?;
+            println!("stored secret {}", name);
+        }
+        "delete" => {
+            if store.delete(name)? {
+                println!("deleted secret {}", name);
+            } else {
+                println!("secret {} does not exist", name);
+            }
+        }
+        _ => return Err(usage.to_string()),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Secrets held in memory
+    #[derive(Debug)]
+    struct Fixed(&'static [(&'static str, &'static str)]);
+
+    impl SecretProvider for Fixed {
+        fn get(&self, name: &str) -> Result<Option<String>, String> {
+            Ok(self
+                .0
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, v)| v.to_string()))
+        }
+    }
+
+    #[test]
+    fn test_parse_placeholders() {
+        assert_eq!(
+            parse("Bearer {{secret:TOKEN}}").unwrap(),
+            [("Bearer ", Some("TOKEN")), ("", None)]
+        );
+        assert_eq!(
+            parse("{{secret:a.b}}-{{secret:c_d}}").unwrap(),
+            [("", Some("a.b")), ("-", Some("c_d")), ("", None)]
+        );
+        assert_eq!(
+            parse("no secrets {{}}").unwrap(),
+            [("no secrets {{}}", None)]
+        );
+        assert_eq!(
+            parse("Bearer {{secret:TOKEN").unwrap_err(),
+            "unterminated {{secret:...}} placeholder"
+        );
+        assert!(parse("{{secret:}}").is_err());
+        assert!(parse("{{secret:../etc/passwd}}").is_err());
+        assert!(parse("{{secret:.hidden}}").is_err());
+    }
+
+    #[test]
+    fn test_validate_and_mask() {
+        let mut errors = Vec::new();
+        validate(
+            "Bearer {{secret:TOKEN}}",
+            "headers.Authorization",
+            &mut errors,
+        );
+        assert!(errors.is_empty());
+        validate(
+            "Bearer {{secret:TOKEN",
+            "headers.Authorization",
+            &mut errors,
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "headers.Authorization");
+
+        assert_eq!(
+            mask("https://a.test/{{secret:PATH}}?k={{secret:KEY}}", "x"),
+            "https://a.test/x?k=x"
+        );
+        assert_eq!(
+            mask("{{secret:KEY", "x"),
+            "{{secret:KEY",
+            "Malformed text is kept"
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let provider = Fixed(&[("TOKEN", "tok_1"), ("KEY", "k")]);
+        assert_eq!(
+            resolve("Bearer {{secret:TOKEN}}", &provider).unwrap(),
+            "Bearer tok_1"
+        );
+        assert_eq!(
+            resolve("{{secret:KEY}}{{secret:KEY}}", &provider).unwrap(),
+            "kk"
+        );
+        assert_eq!(resolve("plain", &provider).unwrap(), "plain");
+        assert_eq!(
+            resolve("{{secret:MISSING}}", &provider).unwrap_err(),
+            "secret MISSING is not defined"
+        );
+    }
+
+    #[test]
+    fn test_env_provider() {
+        let name = format!("TOKEN_{}", std::process::id());
+        let prefix = "BADGER_SECRETS_TEST_";
+        // SAFETY: no other test reads or writes this variable
+        unsafe { std::env::set_var(format!("{}{}", prefix, name), "tok_env") };
+        let provider = EnvProvider {
+            prefix: prefix.to_string(),
+        };
+        let text = format!("Bearer {{{{secret:{}}}}}", name);
+        assert_eq!(resolve(&text, &provider).unwrap(), "Bearer tok_env");
+        assert_eq!(provider.get("MISSING").unwrap(), None);
+    }
+
+    #[test]
+    fn test_directory_provider() {
+        let dir = std::env::temp_dir().join(format!("badger-secrets-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(dir.join("TOKEN"), "tok_file\n").unwrap();
+        let provider = DirectoryProvider { dir: dir.clone() };
+
+        assert_eq!(
+            resolve("Bearer {{secret:TOKEN}}", &provider).unwrap(),
+            "Bearer tok_file",
+            "The trailing newline is not part of the secret"
+        );
+        assert_eq!(provider.get("MISSING").unwrap(), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_secret_hosts() {
+        let hosts =
+            SecretHosts::parse("TOKEN=api.partner.com, TOKEN=EU.partner.com,KEY=b.test").unwrap();
+        let check = |text, host| hosts.check(text, Some(host));
+
+        assert!(check("Bearer {{secret:TOKEN}}", "api.partner.com").is_ok());
+        assert!(check("Bearer {{secret:TOKEN}}", "eu.partner.com").is_ok());
+        assert_eq!(
+            check("Bearer {{secret:TOKEN}}", "attacker.test").unwrap_err(),
+            "secret TOKEN may not be sent to attacker.test"
+        );
+        assert!(check("{{secret:TOKEN}}{{secret:KEY}}", "b.test").is_err());
+        assert!(
+            check("{{secret:OTHER}}", "b.test").is_err(),
+            "Unlisted secrets go nowhere"
+        );
+        assert!(check("no secrets", "attacker.test").is_ok());
+        assert!(hosts.check("{{secret:KEY}}", None).is_err());
+
+        assert!(SecretHosts::parse("TOKEN").is_err());
+        assert!(SecretHosts::parse("bad name=a.test").is_err());
+        assert!(SecretHosts::parse("TOKEN=a.test:8443").is_err());
+        assert!(SecretHosts::parse("TOKEN=a.test/path").is_err());
+    }
+}
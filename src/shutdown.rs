@@ -0,0 +1,148 @@
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Phase {
+    Running,
+    /// No new jobs are claimed, running ones may still finish
+    Draining,
+    /// The grace period is over, jobs still running are released
+    Expired,
+}
+
+/// The process's progress through a graceful shutdown, shared by every task
+#[derive(Clone)]
+pub struct Shutdown(watch::Receiver<Phase>);
+
+impl Shutdown {
+    /// Waits for SIGINT or SIGTERM, then drains for up to `grace`. A second
+    /// signal ends the grace period at once.
+    pub fn listen(grace: Duration) -> Self {
+        Self::on(grace, signal)
+    }
+
+    /// Like `listen`, with `signal` resolving on each shutdown request
+    fn on<F, Fut>(grace: Duration, signal: F) -> Self
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        let (tx, rx) = watch::channel(Phase::Running);
+
+        tokio::spawn(async move {
+            signal().await;
+            info!(
+                "Shutting down, running jobs have {}s to finish",
+                grace.as_secs()
+            );
+            let _ = tx.send(Phase::Draining);
+
+            tokio::select! {
+                _ = tokio::time::sleep(grace) => {}
+                _ = signal() => warn!("Received a second signal, not waiting for running jobs"),
+            }
+            let _ = tx.send(Phase::Expired);
+        });
+
+        Shutdown(rx)
+    }
+
+    pub fn is_draining(&self) -> bool {
+        *self.0.borrow() >= Phase::Draining
+    }
+
+    /// Resolves once a shutdown has started
+    pub async fn draining(&self) {
+        self.reached(Phase::Draining).await
+    }
+
+    /// Resolves once the grace period is over
+    pub async fn expired(&self) {
+        self.reached(Phase::Expired).await
+    }
+
+    async fn reached(&self, phase: Phase) {
+        let mut rx = self.0.clone();
+        // The sender only goes away after the last phase, which satisfies every wait
+        let _ = rx.wait_for(|current| *current >= phase).await;
+    }
+}
+
+async fn signal() {
+    let interrupt = tokio::signal::ctrl_c();
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install the SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => {}
+        _ = terminate => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::sync::Notify;
+    use tokio::time::timeout;
+
+    /// A shutdown requested by `notify_one` on the returned `Notify`
+    fn shutdown(grace: Duration) -> (Arc<Notify>, Shutdown) {
+        let requests = Arc::new(Notify::new());
+        let notify = requests.clone();
+        let shutdown = Shutdown::on(grace, move || {
+            let notify = notify.clone();
+            async move { notify.notified().await }
+        });
+        (requests, shutdown)
+    }
+
+    const SOON: Duration = Duration::from_secs(1);
+
+    #[tokio::test]
+    async fn test_drains_then_expires_after_the_grace_period() {
+        let (requests, shutdown) = shutdown(Duration::from_millis(100));
+        assert!(!shutdown.is_draining());
+        assert!(
+            timeout(Duration::from_millis(50), shutdown.draining())
+                .await
+                .is_err()
+        );
+
+        requests.notify_one();
+        timeout(SOON, shutdown.draining()).await.unwrap();
+        assert!(shutdown.clone().is_draining(), "Every clone sees the phase");
+        assert!(
+            timeout(Duration::from_millis(20), shutdown.expired())
+                .await
+                .is_err(),
+            "Running jobs get the grace period"
+        );
+        timeout(SOON, shutdown.expired()).await.unwrap();
+        timeout(SOON, shutdown.draining()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_second_signal_ends_the_grace_period() {
+        let (requests, shutdown) = shutdown(Duration::from_secs(3600));
+        requests.notify_one();
+        timeout(SOON, shutdown.draining()).await.unwrap();
+        assert!(
+            timeout(Duration::from_millis(50), shutdown.expired())
+                .await
+                .is_err()
+        );
+
+        requests.notify_one();
+        timeout(SOON, shutdown.expired()).await.unwrap();
+    }
+}
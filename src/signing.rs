@@ -0,0 +1,69 @@
+use crate::entity::job;
+use badger_webhooks::Webhook;
+use std::collections::HashMap;
+
+/// Secrets used to sign deliveries. A job's own secret wins over its host's,
+/// which wins over the global one.
+#[derive(Debug, Clone, Default)]
+pub struct SigningConfig {
+    pub global: Option<Webhook>,
+    pub by_host: HashMap<String, Webhook>,
+}
+
+impl SigningConfig {
+    pub fn for_job(&self, job: &job::Model) -> Option<Webhook> {
+        if let Some(secret) = &job.signing_secret {
+            match Webhook::new(secret) {
+                Ok(webhook) => return Some(webhook),
+                Err(e) => tracing::error!("Job {} has an unusable signing secret: {}", job.id, e),
+            }
+        }
+        job.host
+            .as_ref()
+            .and_then(|host| self.by_host.get(host))
+            .or(self.global.as_ref())
+            .cloned()
+    }
+}
+
+/// Parses `host=secret` pairs separated by commas, such as
+/// `api.example.com=whsec_...,hooks.example.org=whsec_...`
+pub fn parse_host_secrets(list: &str) -> Result<HashMap<String, Webhook>, String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .enumerate()
+        .map(|(i, entry)| {
+            // Entries are named by position, one may be a bare secret, whose base64
+            // padding also splits on '='
+            let position = i + 1;
+            let (host, secret) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("entry {} is not host=secret", position))?;
+            let webhook =
+                Webhook::new(secret.trim()).map_err(|e| format!("entry {}: {}", position, e))?;
+            Ok((host.trim().to_lowercase(), webhook))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_host_secrets() {
+        let secret = "whsec_MfKQ9r8GKYqrTwjUPD8ILPZIo2LaLaSw";
+        let hosts = parse_host_secrets(&format!(" API.example.com={}, ,b.test={}", secret, secret))
+            .unwrap();
+        assert_eq!(hosts.len(), 2);
+        assert!(hosts.contains_key("api.example.com"));
+
+        // A bare secret, with and without padding, is not echoed
+        for bare in [secret, "whsec_MfKQ9r8GKYqrTwjUPD8ILPZIo2LaLaSw=="] {
+            let error = parse_host_secrets(&format!("a.test={},{}", secret, bare)).unwrap_err();
+            assert!(error.starts_with("entry 2"), "{}", error);
+            assert!(!error.contains("MfKQ9r8G"), "{}", error);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use sea_orm::sqlx::postgres::PgListener;
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, DbErr, Statement};
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::{Notify, futures::Notified};
+use tracing::info;
+
+/// Channel notified by the `notify_job_ready` trigger when a job is inserted or
+/// requeued and already due
+pub const CHANNEL: &str = "badger_job_ready";
+
+/// Registers for the next wakeup. Taken before looking for jobs, so a job that
+/// becomes ready right after the claim query still wakes the caller.
+pub fn ready(wakeup: &Notify) -> Pin<Box<Notified<'_>>> {
+    let mut ready = Box::pin(wakeup.notified());
+    ready.as_mut().enable();
+    ready
+}
+
+/// Wakes the workers of every process, e.g. after a queue is resumed. On SQLite
+/// this does nothing, workers there find the jobs on their next poll.
+pub async fn notify(db: &DatabaseConnection) -> Result<(), DbErr> {
+    if db.get_database_backend() != DbBackend::Postgres {
+        return Ok(());
+    }
+
+    db.execute(Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT pg_notify($1, '')",
+        [CHANNEL.into()],
+    ))
+    .await?;
+    Ok(())
+}
+
+/// Wakes every idle worker when Postgres reports a ready job. A single `LISTEN`
+/// connection, taken from the pool, serves all workers. On SQLite this returns
+/// at once and workers keep polling.
+pub async fn listen_task(db: DatabaseConnection, wakeup: Arc<Notify>) {
+    if db.get_database_backend() != DbBackend::Postgres {
+        return;
+    }
+
+    loop {
+        match PgListener::connect_with(db.get_postgres_connection_pool()).await {
+            Ok(mut listener) => match listener.listen(CHANNEL).await {
+                Ok(()) => {
+                    info!("Listening for ready jobs on {}", CHANNEL);
+                    loop {
+                        match listener.try_recv().await {
+                            Ok(Some(_)) => wakeup.notify_waiters(),
+                            // The connection dropped and is reopened on the next call.
+                            // Anything sent meanwhile was lost, so look for work now.
+                            Ok(None) => {
+                                tracing::warn!("Lost the {} listener, reconnecting", CHANNEL);
+                                wakeup.notify_waiters();
+                            }
+                            Err(e) => {
+                                tracing::error!("Listening on {} failed: {}", CHANNEL, e);
+                                break;
+                            }
+                        }
+                    }
+                }
+                Err(e) => tracing::error!("Cannot LISTEN on {}: {}", CHANNEL, e),
+            },
+            Err(e) => tracing::error!("Cannot open a listener connection: {}", e),
+        }
+
+        // Workers still poll while the listener is down
+        wakeup.notify_waiters();
+        tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_wakeup_before_the_wait_is_not_lost() {
+        let wakeup = Notify::new();
+
+        // The job arrives between the claim query and the wait
+        let registered = ready(&wakeup);
+        wakeup.notify_waiters();
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), registered)
+                .await
+                .is_ok()
+        );
+
+        // Sent before registering, it is left to the poll
+        wakeup.notify_waiters();
+        let registered = ready(&wakeup);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), registered)
+                .await
+                .is_err()
+        );
+    }
+}
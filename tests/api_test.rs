@@ -2,7 +2,7 @@
 //! These tests verify the HTTP API endpoints
 
 use chrono::Utc;
-use sea_orm::{ConnectionTrait, Database, DatabaseConnection, DbBackend, Statement};
+use sea_orm::{DbBackend, Statement};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use uuid::Uuid;
@@ -15,43 +15,9 @@ struct JobRequest {
     body: Option<serde_json::Value>,
     run_at: Option<chrono::DateTime<chrono::Utc>>,
     cron: Option<String>,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct JobResponse {
-    id: String,
-    url: String,
-    method: String,
-    status: String,
-}
-
-async fn setup_test_db() -> DatabaseConnection {
-    let db = Database::connect("sqlite::memory:")
-        .await
-        .expect("Failed to connect to database");
-    
-    db.execute_unprepared(
-        r#"CREATE TABLE job (
-            unique_id TEXT PRIMARY KEY NOT NULL,
-            id TEXT NOT NULL,
-            url TEXT NOT NULL,
-            method TEXT NOT NULL,
-            headers TEXT NOT NULL DEFAULT '{}',
-            body TEXT NOT NULL DEFAULT 'null',
-            retries INTEGER NOT NULL DEFAULT 0,
-            attempts INTEGER NOT NULL DEFAULT 0,
-            status TEXT NOT NULL DEFAULT 'Pending',
-            next_run_at TEXT NOT NULL,
-            created_at TEXT NOT NULL,
-            updated_at TEXT NOT NULL,
-            check_in TEXT,
-            cron TEXT
-        )"#
-    )
-    .await
-    .expect("Failed to create table");
-    
-    db
+    timeout_ms: Option<i32>,
+    body_encoding: Option<String>,
+    content_type: Option<String>,
 }
 
 #[cfg(test)]
@@ -68,6 +34,9 @@ mod tests {
             body: Some(json!({"key": "value"})),
             run_at: None,
             cron: None,
+            timeout_ms: None,
+            body_encoding: None,
+            content_type: None,
         };
         
         let payload = serde_json::to_string(&job_request).unwrap();
@@ -195,28 +164,28 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_exponential_backoff_calculation() {
-        // Test the exponential backoff formula used in Badger
-        fn calculate_backoff(attempts: u32) -> i64 {
-            let base = 1000 * 2i64.pow(attempts.max(0));
-            base
-        }
-        
-        // Verify exponential growth
-        assert_eq!(calculate_backoff(0), 1000);      // 1 second
-        assert_eq!(calculate_backoff(1), 2000);      // 2 seconds
-        assert_eq!(calculate_backoff(2), 4000);      // 4 seconds
-        assert_eq!(calculate_backoff(3), 8000);      // 8 seconds
-        assert_eq!(calculate_backoff(4), 16000);     // 16 seconds
-        assert_eq!(calculate_backoff(5), 32000);     // 32 seconds
-        
-        // Verify it grows exponentially
-        for i in 1..10 {
-            let current = calculate_backoff(i);
-            let previous = calculate_backoff(i - 1);
-            assert!(current > previous, "Backoff should increase with attempts");
-            assert_eq!(current, previous * 2, "Backoff should double each attempt");
-        }
+    async fn test_signed_delivery() {
+        use badger_webhooks::Webhook;
+
+        let secret = "whsec_MfKQ9r8GKYqrTwjUPD8ILPZIo2LaLaSw";
+        let webhook = Webhook::new(secret).unwrap();
+        let msg_id = Uuid::new_v4().to_string();
+        // The signature covers the bytes on the wire, here a form body
+        let payload = b"amount=10&tag=a&tag=b+c";
+
+        let first = Utc::now().timestamp();
+        let signature = webhook.sign(&msg_id, first, payload);
+        assert!(signature.starts_with("v1,"));
+        assert!(webhook.verify(&msg_id, &first.to_string(), &signature, payload, Utc::now().timestamp()).is_ok());
+
+        // A retry is signed with its own timestamp, so an old signature cannot be replayed
+        let retry = first + 600;
+        assert_ne!(webhook.sign(&msg_id, retry, payload), signature);
+        assert!(webhook.verify(&msg_id, &first.to_string(), &signature, payload, retry).is_err());
+
+        // The JSON value is not what gets signed
+        assert!(webhook.verify(&msg_id, &first.to_string(), &signature, br#"{"amount":10}"#, first).is_err());
+        assert!(Webhook::new("whsec_%%%").is_err(), "Invalid secrets are rejected on submit");
     }
 
     #[tokio::test]
@@ -308,6 +277,9 @@ mod tests {
             })),
             run_at: None,
             cron: Some("*/5 * * * *".to_string()),
+            timeout_ms: Some(120_000),
+            body_encoding: Some("form".to_string()),
+            content_type: Some("application/x-www-form-urlencoded".to_string()),
         };
         
         let serialized = serde_json::to_string(&original).unwrap();
@@ -316,6 +288,8 @@ mod tests {
         assert_eq!(original.url, deserialized.url);
         assert_eq!(original.method, deserialized.method);
         assert_eq!(original.cron, deserialized.cron);
+        assert_eq!(original.timeout_ms, deserialized.timeout_ms);
+        assert_eq!(original.body_encoding, deserialized.body_encoding);
     }
 
     #[tokio::test]
@@ -348,7 +322,7 @@ mod tests {
     #[tokio::test]
     async fn test_status_enum_values() {
         // Test that status values match expected strings
-        let statuses = vec!["Pending", "Running", "Success", "Failure"];
+        let statuses = vec!["Pending", "Running", "Success", "Failure", "Cancelled"];
         
         for status in statuses {
             // Verify status can be used in SQL queries
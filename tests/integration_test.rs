@@ -11,6 +11,7 @@ pub enum StatusEnum {
     Running,
     Success,
     Failure,
+    Cancelled,
 }
 
 impl std::fmt::Display for StatusEnum {
@@ -20,6 +21,7 @@ impl std::fmt::Display for StatusEnum {
             StatusEnum::Running => write!(f, "Running"),
             StatusEnum::Success => write!(f, "Success"),
             StatusEnum::Failure => write!(f, "Failure"),
+            StatusEnum::Cancelled => write!(f, "Cancelled"),
         }
     }
 }
@@ -44,11 +46,59 @@ async fn setup_db() -> DatabaseConnection {
             created_at TEXT NOT NULL,
             updated_at TEXT NOT NULL,
             check_in TEXT,
-            cron TEXT
+            cron TEXT,
+            host TEXT,
+            max_attempts INTEGER,
+            response_status INTEGER,
+            response_headers TEXT,
+            response_body TEXT,
+            response_truncated BOOLEAN,
+            response_length INTEGER,
+            timeout_ms INTEGER,
+            retry_policy TEXT,
+            failure_reason TEXT,
+            body_encoding TEXT,
+            content_type TEXT,
+            signing_secret TEXT,
+            encryption_key_id TEXT,
+            encrypted_data_key TEXT,
+            priority INTEGER NOT NULL DEFAULT 0,
+            queue TEXT NOT NULL DEFAULT 'default'
         )"#
     )
     .await
     .expect("Failed to create table");
+
+    db.execute_unprepared(
+        r#"CREATE TABLE job_attempt (
+            id TEXT PRIMARY KEY NOT NULL,
+            job_id TEXT NOT NULL,
+            attempt INTEGER NOT NULL,
+            worker_id TEXT NOT NULL,
+            started_at TEXT NOT NULL,
+            finished_at TEXT NOT NULL,
+            latency_ms INTEGER NOT NULL,
+            response_status INTEGER,
+            response_headers TEXT,
+            response_body TEXT,
+            response_truncated BOOLEAN,
+            response_length INTEGER,
+            error TEXT,
+            failure_reason TEXT
+        )"#
+    )
+    .await
+    .expect("Failed to create attempt table");
+
+    db.execute_unprepared(
+        r#"CREATE TABLE queue (
+            name TEXT PRIMARY KEY NOT NULL,
+            paused BOOLEAN NOT NULL DEFAULT FALSE,
+            updated_at TEXT NOT NULL
+        )"#
+    )
+    .await
+    .expect("Failed to create queue table");
     
     db
 }
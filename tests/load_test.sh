@@ -49,7 +49,7 @@ submit_jobs() {
                 \"headers\": {\"X-Job-ID\": \"$job_num\"},
                 \"body\": {\"test\": \"load_test\", \"iteration\": $job_num}
             }")
-        echo "$response" >> "$JOB_IDS"
+        echo "$response" | grep -o '"id":"[^"]*"' | cut -d'"' -f4 >> "$JOB_IDS"
     done
 }
 
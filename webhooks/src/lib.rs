@@ -0,0 +1,170 @@
+//! Signing and verification of badger webhook deliveries.
+//!
+//! Deliveries follow the [Standard Webhooks](https://www.standardwebhooks.com) scheme:
+//! every signed request carries `webhook-id`, `webhook-timestamp` and `webhook-signature`
+//! headers, and the signature is an HMAC-SHA256 of `{id}.{timestamp}.{body}` keyed with
+//! the shared secret.
+//!
+//! A receiver checks a delivery with [`Webhook::verify`]:
+//!
+//! ```
+//! use badger_webhooks::Webhook;
+//!
+//! let webhook = Webhook::new("whsec_MfKQ9r8GKYqrTwjUPD8ILPZIo2LaLaSw").unwrap();
+//! let body = br#"{"test": 2432232314}"#;
+//! let signature = webhook.sign("msg_p5jXN8AQM9LWM0D4loKWxJek", 1614265330, body);
+//!
+//! webhook
+//!     .verify("msg_p5jXN8AQM9LWM0D4loKWxJek", "1614265330", &signature, body, 1614265330)
+//!     .unwrap();
+//! ```
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use sha2::{Digest, Sha256};
+
+pub const ID_HEADER: &str = "webhook-id";
+pub const TIMESTAMP_HEADER: &str = "webhook-timestamp";
+pub const SIGNATURE_HEADER: &str = "webhook-signature";
+
+/// Prefix of an encoded secret, followed by the base64 key
+pub const SECRET_PREFIX: &str = "whsec_";
+
+/// How far a delivery's timestamp may be from the receiver's clock, in seconds
+pub const DEFAULT_TOLERANCE_SECS: i64 = 5 * 60;
+
+const BLOCK_SIZE: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The secret is not base64, with or without the `whsec_` prefix
+    InvalidSecret,
+    /// `webhook-timestamp` is not a number of seconds
+    InvalidTimestamp,
+    /// The timestamp is outside the tolerance, which is how replays are rejected
+    TimestampOutOfRange,
+    /// None of the `v1` signatures match
+    SignatureMismatch,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidSecret => write!(
+                f,
+                "signing secret must be base64, optionally prefixed with {}",
+                SECRET_PREFIX
+            ),
+            Error::InvalidTimestamp => write!(f, "invalid {} header", TIMESTAMP_HEADER),
+            Error::TimestampOutOfRange => {
+                write!(f, "{} is too far from the current time", TIMESTAMP_HEADER)
+            }
+            Error::SignatureMismatch => write!(f, "no matching signature in {}", SIGNATURE_HEADER),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// A signing secret, shared by badger and the receiver
+#[derive(Clone)]
+pub struct Webhook {
+    key: Vec<u8>,
+    tolerance_secs: i64,
+}
+
+impl std::fmt::Debug for Webhook {
+    // Never print the key
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Webhook")
+            .field("tolerance_secs", &self.tolerance_secs)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Webhook {
+    /// Parses a `whsec_...` secret. The prefix is optional.
+    pub fn new(secret: &str) -> Result<Self, Error> {
+        let encoded = secret.strip_prefix(SECRET_PREFIX).unwrap_or(secret);
+        let key = STANDARD.decode(encoded).map_err(|_| Error::InvalidSecret)?;
+        if key.is_empty() {
+            return Err(Error::InvalidSecret);
+        }
+        Ok(Self {
+            key,
+            tolerance_secs: DEFAULT_TOLERANCE_SECS,
+        })
+    }
+
+    /// Changes how old or new a delivery may be and still verify
+    pub fn with_tolerance(mut self, seconds: i64) -> Self {
+        self.tolerance_secs = seconds;
+        self
+    }
+
+    /// The `webhook-signature` value for one delivery, e.g. `v1,<base64>`
+    pub fn sign(&self, id: &str, timestamp: i64, payload: &[u8]) -> String {
+        let mut content = Vec::with_capacity(id.len() + payload.len() + 24);
+        content.extend_from_slice(id.as_bytes());
+        content.push(b'.');
+        content.extend_from_slice(timestamp.to_string().as_bytes());
+        content.push(b'.');
+        content.extend_from_slice(payload);
+        format!("v1,{}", STANDARD.encode(hmac_sha256(&self.key, &content)))
+    }
+
+    /// Checks a delivery against the three `webhook-*` header values and the raw body.
+    /// `now` is the receiver's current Unix time in seconds.
+    pub fn verify(
+        &self,
+        id: &str,
+        timestamp: &str,
+        signatures: &str,
+        payload: &[u8],
+        now: i64,
+    ) -> Result<(), Error> {
+        let timestamp: i64 = timestamp
+            .trim()
+            .parse()
+            .map_err(|_| Error::InvalidTimestamp)?;
+        if (now - timestamp).abs() > self.tolerance_secs {
+            return Err(Error::TimestampOutOfRange);
+        }
+
+        let expected = self.sign(id, timestamp, payload);
+        // Several space separated signatures are sent while a secret is being rotated
+        let matched = signatures
+            .split(' ')
+            .filter(|signature| signature.starts_with("v1,"))
+            .any(|signature| constant_time_eq(signature.as_bytes(), expected.as_bytes()));
+        if matched {
+            Ok(())
+        } else {
+            Err(Error::SignatureMismatch)
+        }
+    }
+}
+
+/// HMAC (RFC 2104) over SHA-256
+pub fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.map(|b| b ^ 0x36));
+    inner.update(message);
+    let inner = inner.finalize();
+
+    let mut outer = Sha256::new();
+    outer.update(block.map(|b| b ^ 0x5c));
+    outer.update(inner);
+    outer.finalize().into()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,77 @@
+//! Signature tests for badger-webhooks
+//! The vectors come from the Standard Webhooks reference implementations
+
+use badger_webhooks::{Error, Webhook, hmac_sha256};
+
+const SECRET: &str = "whsec_MfKQ9r8GKYqrTwjUPD8ILPZIo2LaLaSw";
+const MSG_ID: &str = "msg_p5jXN8AQM9LWM0D4loKWxJek";
+const TIMESTAMP: i64 = 1614265330;
+const PAYLOAD: &[u8] = br#"{"test": 2432232314}"#;
+const SIGNATURE: &str = "v1,g0hM9SsE+OTPJTGt/tmIKtSyZlE3uFJELVlNIOLJ1OE=";
+
+#[test]
+fn test_hmac_sha256_rfc4231() {
+    // RFC 4231 test cases 2 and 6, the second with a key longer than one block
+    assert_eq!(
+        hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")),
+        "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+    assert_eq!(
+        hex(&hmac_sha256(&[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First")),
+        "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+    );
+}
+
+#[test]
+fn test_sign_matches_reference() {
+    let webhook = Webhook::new(SECRET).unwrap();
+    assert_eq!(webhook.sign(MSG_ID, TIMESTAMP, PAYLOAD), SIGNATURE);
+
+    // The prefix is optional
+    let bare = Webhook::new(SECRET.trim_start_matches("whsec_")).unwrap();
+    assert_eq!(bare.sign(MSG_ID, TIMESTAMP, PAYLOAD), SIGNATURE);
+}
+
+#[test]
+fn test_verify() {
+    let webhook = Webhook::new(SECRET).unwrap();
+    let ts = TIMESTAMP.to_string();
+
+    assert_eq!(webhook.verify(MSG_ID, &ts, SIGNATURE, PAYLOAD, TIMESTAMP + 60), Ok(()));
+    assert_eq!(
+        webhook.verify(MSG_ID, &ts, &format!("v1,bm90IGl0 {}", SIGNATURE), PAYLOAD, TIMESTAMP),
+        Ok(()),
+        "Any of several signatures may match"
+    );
+    assert_eq!(
+        webhook.verify(MSG_ID, &ts, SIGNATURE, br#"{"test": 2432232315}"#, TIMESTAMP),
+        Err(Error::SignatureMismatch)
+    );
+    assert_eq!(
+        webhook.verify("msg_other", &ts, SIGNATURE, PAYLOAD, TIMESTAMP),
+        Err(Error::SignatureMismatch)
+    );
+    assert_eq!(
+        webhook.verify(MSG_ID, &ts, SIGNATURE, PAYLOAD, TIMESTAMP + 301),
+        Err(Error::TimestampOutOfRange),
+        "Old deliveries are rejected as replays"
+    );
+    assert_eq!(
+        webhook.verify(MSG_ID, "yesterday", SIGNATURE, PAYLOAD, TIMESTAMP),
+        Err(Error::InvalidTimestamp)
+    );
+    assert_eq!(
+        webhook.with_tolerance(3600).verify(MSG_ID, &ts, SIGNATURE, PAYLOAD, TIMESTAMP + 301),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_invalid_secret() {
+    assert_eq!(Webhook::new("whsec_not base64!").unwrap_err(), Error::InvalidSecret);
+    assert_eq!(Webhook::new("whsec_").unwrap_err(), Error::InvalidSecret);
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}